@@ -1,6 +1,5 @@
 use std::{
     collections::HashMap,
-    io::Write,
     net::TcpStream,
     sync::{Arc, Mutex},
     thread, time,
@@ -27,6 +26,7 @@ fn input_to_action(input: Input) -> GameAction {
 }
 
 pub fn agent_thread(mut stream: TcpStream, game_board: Arc<Mutex<Option<PlayerGame>>>) {
+    let mut last_fall = time::Instant::now();
     loop {
         thread::sleep(time::Duration::from_millis(5));
         let mut input_to_do = Vec::new();
@@ -34,8 +34,8 @@ pub fn agent_thread(mut stream: TcpStream, game_board: Arc<Mutex<Option<PlayerGa
             let matrix = game.matrix();
             if let Some(tetrimino) = game.current_tetrimino() {
                 if game.stocked_tetrimino() == TetriminoType::None {
-                    stream
-                        .write(&ClientRequest::Input(Input::StockTetrimino).into_bytes())
+                    ClientRequest::Input(Input::StockTetrimino)
+                        .write_to(&mut stream)
                         .unwrap();
                 } else {
                     let stocked_tetrimino = game.stocked_tetrimino();
@@ -51,16 +51,18 @@ pub fn agent_thread(mut stream: TcpStream, game_board: Arc<Mutex<Option<PlayerGa
                     .1;
                 }
             } else {
-                stream
-                    .write(&ClientRequest::Input(Input::Fall).into_bytes())
-                    .unwrap();
-                thread::sleep(time::Duration::from_millis(1));
+                // The server refuses the falls sent faster than the gravity
+                let call_every = time::Duration::from_millis(game.gravity().call_every_ms.into());
+                if last_fall.elapsed() >= call_every {
+                    last_fall = time::Instant::now();
+                    ClientRequest::Input(Input::Fall)
+                        .write_to(&mut stream)
+                        .unwrap();
+                }
             }
         }
         for i in input_to_do.iter() {
-            stream
-                .write(&ClientRequest::Input(*i).into_bytes())
-                .unwrap();
+            ClientRequest::Input(*i).write_to(&mut stream).unwrap();
             thread::sleep(time::Duration::from_millis(40));
         }
     }
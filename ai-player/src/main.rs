@@ -1,4 +1,9 @@
-use fetris_protocol::{game::PlayerGame, ClientRequest, ServerRequest};
+use fetris_protocol::{
+    game::PlayerGame,
+    game_mode::GameOptions,
+    handshake::{self, ClientKind},
+    ClientRequest, ServerRequest,
+};
 use std::env;
 use std::io::{stdout, Write};
 use std::net::TcpStream;
@@ -11,6 +16,7 @@ mod print;
 mod server_receiver;
 
 use client_server_showdown::ActionsQueues;
+use print::OtherPlayers;
 
 fn main() -> Result<(), std::io::Error> {
     if env::args().len() != 2 {
@@ -19,13 +25,18 @@ fn main() -> Result<(), std::io::Error> {
     }
 
     let mut stream = TcpStream::connect(env::args().nth(1).unwrap())?;
+    if let Err(err) = handshake::hello(&stream, ClientKind::Ai) {
+        println!("{}", err);
+        return Ok(());
+    }
 
     let _hide_cursor = termion::cursor::HideCursor::from(stdout());
 
     let game_board: Arc<Mutex<Option<PlayerGame>>> = Arc::new(Mutex::new(None));
     let action_queues: Arc<Mutex<ActionsQueues>> = Arc::new(Mutex::new(ActionsQueues::new()));
+    let other_players: Arc<Mutex<OtherPlayers>> = Arc::new(Mutex::new(OtherPlayers::default()));
 
-    stream.write(&ClientRequest::AskForAGame.into_bytes())?;
+    stream.write_all(&ClientRequest::AskForAGame(None, GameOptions::default()).to_bytes()?)?;
 
     println!(
         "{}{}Waiting for other players ...",
@@ -33,22 +44,30 @@ fn main() -> Result<(), std::io::Error> {
         termion::cursor::Goto(1, 1),
     );
 
-    {
-        let game =
-            if let Ok(ServerRequest::GameReady(game, _)) = ServerRequest::from_reader(&stream) {
-                game
-            } else {
-                panic!("Invalid first server request");
-            };
+    let mode = {
+        let (game, settings) = if let Ok(ServerRequest::GameReady(game, settings, _)) =
+            ServerRequest::from_reader(&stream)
+        {
+            (game, settings)
+        } else {
+            panic!("Invalid first server request");
+        };
         let mut board = game_board.lock().unwrap();
         *board = Some(game);
-    }
+        settings.mode
+    };
 
-    print::launch_print_thread(game_board.clone(), action_queues.clone());
+    print::launch_print_thread(
+        game_board.clone(),
+        action_queues.clone(),
+        other_players.clone(),
+        mode,
+    );
     server_receiver::launch_server_receiver_thread(
         stream.try_clone().unwrap(),
         action_queues.clone(),
         game_board.clone(),
+        other_players,
     );
 
     agent::agent_thread(stream, game_board);
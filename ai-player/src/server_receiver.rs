@@ -1,18 +1,22 @@
 use std::{
     net::TcpStream,
     sync::{Arc, Mutex},
-    thread, time,
+    thread,
 };
 use termion;
 
-use fetris_protocol::{actions, game::PlayerGame, ServerRequest};
+use fetris_protocol::{actions, game::PlayerGame, ClientRequest, ServerRequest};
 
-use crate::client_server_showdown::ActionsQueues;
+use crate::{
+    client_server_showdown::ActionsQueues,
+    print::{self, OtherPlayers},
+};
 
 pub fn launch_server_receiver_thread(
     reader: TcpStream,
     action_queues: Arc<Mutex<ActionsQueues>>,
     game_board: Arc<Mutex<Option<PlayerGame>>>,
+    other_players: Arc<Mutex<OtherPlayers>>,
 ) {
     thread::spawn(move || loop {
         if let Ok(request) = ServerRequest::from_reader(&reader) {
@@ -23,17 +27,71 @@ pub fn launch_server_receiver_thread(
                 println!("{}-------------", termion::cursor::Goto(4, 12));
                 println!("{}| Game Over |", termion::cursor::Goto(4, 13));
                 println!("{}-------------", termion::cursor::Goto(4, 14));
-                loop {
-                    thread::sleep(time::Duration::from_secs(1));
-                }
             }
             match request {
+                // The agent predicts nothing, its board only follows the server and needs no Resync
                 ServerRequest::MinifiedAction(action) => {
                     let mut action_queues = action_queues.lock().unwrap();
                     let mut board = game_board.lock().unwrap();
                     let _ = actions::apply_action(board.as_mut().unwrap(), action.clone());
                     action_queues.push_server_action(action);
                 }
+                ServerRequest::GravityChanged(gravity) => {
+                    let mut board = game_board.lock().unwrap();
+                    if let Some(board) = board.as_mut() {
+                        board.set_gravity(gravity);
+                    }
+                }
+                ServerRequest::PendingGarbage(pending_garbage) => {
+                    let mut board = game_board.lock().unwrap();
+                    if let Some(board) = board.as_mut() {
+                        board.set_pending_garbage(pending_garbage);
+                    }
+                }
+                // Everyone voted for a rematch, a new match starts
+                ServerRequest::GameReady(game, _, _) => {
+                    let mut action_queues = action_queues.lock().unwrap();
+                    let mut board = game_board.lock().unwrap();
+                    let mut other_players = other_players.lock().unwrap();
+                    *action_queues = ActionsQueues::new();
+                    *board = Some(game);
+                    other_players.games.clear();
+                    other_players.target = None;
+                }
+                ServerRequest::MatchResult(result) => {
+                    let mut board = game_board.lock().unwrap();
+                    *board = None;
+
+                    print::print_match_result(&result);
+                    // The agent always wants to play again
+                    let _ = ClientRequest::Rematch.write_to(&reader);
+                }
+                ServerRequest::RematchVotes(votes, players) => {
+                    print::print_rematch_votes(votes, players);
+                }
+                ServerRequest::PoolClosed => print::print_pool_closed(),
+                ServerRequest::GameFinished(stats) => {
+                    let mut board = game_board.lock().unwrap();
+                    *board = None;
+
+                    print::print_results(&stats);
+                }
+                ServerRequest::PlayerListUpdate(list) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.list = list;
+                }
+                ServerRequest::PlayerAction(id, action) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.apply_action(id, action);
+                }
+                ServerRequest::PlayerSnapshot(snapshot) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.update_game(snapshot);
+                }
+                ServerRequest::PlayerGravityChanged(id, gravity) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.set_gravity(id, gravity);
+                }
                 _ => {}
             }
         } else {
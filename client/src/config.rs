@@ -13,6 +13,15 @@ pub struct Config {
     instant: String,
     accelerate: String,
     stock: String,
+    #[serde(default = "default_target")]
+    target: String,
+    // Shown to the other players, the server picks one otherwise
+    #[serde(default)]
+    name: Option<String>,
+}
+
+fn default_target() -> String {
+    String::from("Tab")
 }
 
 impl Default for Config {
@@ -25,6 +34,8 @@ impl Default for Config {
             instant: String::from("/"),
             accelerate: String::from("Down"),
             stock: String::from("Up"),
+            target: default_target(),
+            name: None,
         }
     }
 }
@@ -38,6 +49,7 @@ impl Config {
             "Up" => Key::Up,
             "Backspace" => Key::Backspace,
             "Enter" => Key::Char('\n'),
+            "Tab" => Key::Char('\t'),
             "Space" => Key::Char(' '),
             "Home" => Key::Home,
             "End" => Key::End,
@@ -65,6 +77,15 @@ impl Config {
 
         ret
     }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // Cycles through the opponents to attack, handled apart since it is not a game input
+    pub fn target_key(&self) -> Key {
+        Self::parse_key(&self.target)
+    }
 }
 
 impl Config {
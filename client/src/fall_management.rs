@@ -1,5 +1,4 @@
 use std::{
-    io::Write,
     net::TcpStream,
     sync::{Arc, Mutex},
     thread, time,
@@ -7,38 +6,56 @@ use std::{
 
 use crate::client_server_showdown::ActionsQueues;
 use fetris_protocol::{
-    game::{GameAction, Input, PlayerGame},
+    game::{Direction, GameAction, Input, PlayerGame},
+    lock_delay::LockDelay,
     tetrimino::TetriminoType,
     ClientRequest,
 };
 
 pub fn fall_management_thread(
-    mut stream: TcpStream,
+    stream: Arc<Mutex<TcpStream>>,
     action_queues: Arc<Mutex<ActionsQueues>>,
-    last_action: Arc<Mutex<time::Instant>>,
+    lock_delay: Arc<Mutex<LockDelay>>,
     board: Arc<Mutex<Option<PlayerGame>>>,
-    falling_interval: u16,
 ) {
-    let falling_interval = time::Duration::from_millis(falling_interval.into());
+    let mut last_fall = time::Instant::now();
 
     thread::spawn(move || loop {
         {
             let mut action_queues = action_queues.lock().unwrap();
             let board = board.lock().unwrap();
-            let mut last_action = last_action.lock().unwrap();
-            if time::Instant::now().duration_since(*last_action) >= falling_interval {
-                *last_action = time::Instant::now();
+            let mut lock_delay = lock_delay.lock().unwrap();
+            let falling_interval = board
+                .as_ref()
+                .map_or(time::Duration::from_secs(0), |board| {
+                    time::Duration::from_millis(board.gravity().call_every_ms.into())
+                });
+            if time::Instant::now().duration_since(last_fall) >= falling_interval {
+                last_fall = time::Instant::now();
                 if let Some(board) = board.as_ref() {
-                    if stream
-                        .write(&ClientRequest::Input(Input::Fall).into_bytes())
+                    if ClientRequest::Input(Input::Fall)
+                        .write_to(&mut *stream.lock().unwrap())
                         .is_err()
                     {
                         //break;
                     }
-                    let (board, _) = action_queues.client_board_prediction(board.clone());
-                    if let Some(_) = board.current_tetrimino() {
-                        action_queues.push_client_action(GameAction::Fall);
+                    let (mut board, _) = action_queues.client_board_prediction(board.clone());
+                    // Garbage from the server may have moved the stack under the tetrimino
+                    lock_delay.update_ground(&board, time::Instant::now());
+                    if let Some(tetrimino) = board.current_tetrimino() {
+                        // The server only confirms falls that move or lock the tetrimino
+                        if tetrimino.can_move_to(board.matrix(), Direction::Down)
+                            || lock_delay.should_lock(time::Instant::now())
+                        {
+                            board.fall();
+                            if let Some(tetrimino) = board.current_tetrimino() {
+                                lock_delay.on_step_down(&tetrimino);
+                            }
+                            lock_delay.update_ground(&board, time::Instant::now());
+                            action_queues.push_client_action(GameAction::Fall);
+                        }
                     } else {
+                        lock_delay.reset();
                         action_queues
                             .push_client_action(GameAction::NewTetrimino(TetriminoType::None));
                     }
@@ -1,18 +1,21 @@
 use std::{
-    io::{stdin, Write},
+    io::stdin,
     net::TcpStream,
     sync::{Arc, Mutex},
     time,
 };
 use termion::{event::Key, input::TermRead};
 
-use crate::{client_server_showdown::ActionsQueues, config::Config};
+use crate::{client_server_showdown::ActionsQueues, config::Config, print::OtherPlayers};
 use fetris_protocol::{
-    actions,
-    game::{Direction, GameAction, Input, PlayerGame},
+    game::{Direction, GameAction, Input, PlayerGame, PlayerId},
+    lock_delay::LockDelay,
     ClientRequest,
 };
 
+// Only read once the match is over, it can share its key with a game input
+const REMATCH_KEY: Key = Key::Char('r');
+
 fn input_to_action(input: Input) -> GameAction {
     match input {
         Input::Left => GameAction::MoveCurrentTetrimino(Direction::Left),
@@ -26,25 +29,75 @@ fn input_to_action(input: Input) -> GameAction {
     }
 }
 
+// Living opponent coming after the current target, wrapping around
+fn next_target(other_players: &OtherPlayers, own_id: PlayerId) -> Option<PlayerId> {
+    let opponents: Vec<_> = other_players
+        .list
+        .iter()
+        .filter(|player| player.id != own_id && !player.dead)
+        .map(|player| player.id)
+        .collect();
+    let position = other_players
+        .target
+        .and_then(|target| opponents.iter().position(|id| *id == target));
+
+    match position {
+        Some(position) => opponents.get((position + 1) % opponents.len()).copied(),
+        None => opponents.first().copied(),
+    }
+}
+
 pub fn keyboard_listen(
-    mut stream: TcpStream,
+    stream: Arc<Mutex<TcpStream>>,
     action_queues: Arc<Mutex<ActionsQueues>>,
     config: Config,
     game_board: Arc<Mutex<Option<PlayerGame>>>,
-    last_action: Arc<Mutex<time::Instant>>,
+    lock_delay: Arc<Mutex<LockDelay>>,
+    other_players: Arc<Mutex<OtherPlayers>>,
 ) {
+    let target_key = config.target_key();
     let config = config.to_hashmap();
     let stdin = stdin();
     for c in stdin.keys() {
         let c = c.unwrap();
         if c == Key::Ctrl('c') {
             println!("{}{}", termion::cursor::Goto(1, 1), termion::clear::All);
-            stream.shutdown(std::net::Shutdown::Both).unwrap();
+            // Leaving on purpose, the connection must not be resumed
+            *game_board.lock().unwrap() = None;
+            stream
+                .lock()
+                .unwrap()
+                .shutdown(std::net::Shutdown::Both)
+                .unwrap();
             break;
         }
+        if c == REMATCH_KEY && game_board.lock().unwrap().is_none() {
+            if ClientRequest::Rematch
+                .write_to(&mut *stream.lock().unwrap())
+                .is_ok()
+            {
+                lock_delay.lock().unwrap().reset();
+            }
+            continue;
+        }
+        if c == target_key {
+            let board = game_board.lock().unwrap();
+            if let Some(board) = &*board {
+                let mut other_players = other_players.lock().unwrap();
+                if let Some(target) = next_target(&other_players, board.id()) {
+                    if ClientRequest::SetTarget(target)
+                        .write_to(&mut *stream.lock().unwrap())
+                        .is_ok()
+                    {
+                        other_players.target = Some(target);
+                    }
+                }
+            }
+            continue;
+        }
         if let Some(input) = config.get(&c) {
-            if stream
-                .write(&ClientRequest::Input(*input).into_bytes())
+            if ClientRequest::Input(*input)
+                .write_to(&mut *stream.lock().unwrap())
                 .is_err()
             {
                 //break;
@@ -55,17 +108,30 @@ pub fn keyboard_listen(
                 if let Some(board) = &*board {
                     let action_result =
                         action_queues.action_result(board, input_to_action(input.clone()));
-                    if action_result != Err(actions::ApplyActionError::InvalidActionNoResetTimer) {
-                        let mut last_action = last_action.lock().unwrap();
-                        *last_action = time::Instant::now();
-                    }
                     if action_result.is_ok() {
                         action_queues.push_client_action(input_to_action(*input));
+
+                        let (board, _) = action_queues.client_board_prediction(board.clone());
+                        let now = time::Instant::now();
+                        let mut lock_delay = lock_delay.lock().unwrap();
+                        match input {
+                            Input::Left | Input::Right | Input::Rotate | Input::RotateRevert => {
+                                lock_delay.on_move(now)
+                            }
+                            Input::Acceleration => {
+                                if let Some(tetrimino) = board.current_tetrimino() {
+                                    lock_delay.on_step_down(&tetrimino);
+                                }
+                            }
+                            Input::StockTetrimino => lock_delay.reset(),
+                            _ => {}
+                        }
+                        lock_delay.update_ground(&board, now);
                     }
                 }
             }
         }
-        if stream.peer_addr().is_err() {
+        if stream.lock().unwrap().peer_addr().is_err() {
             //break;
         }
     }
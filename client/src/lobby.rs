@@ -0,0 +1,213 @@
+use std::{
+    io::{stdin, Write},
+    net::TcpStream,
+    thread,
+};
+
+use fetris_protocol::{
+    game::{GameSettings, PlayerGame, SessionToken},
+    game_mode::{GameMode, GameOptions},
+    pool::{PoolId, PoolInfos},
+    room::{RoomInfos, RoomSettings},
+    BadRequestReason, ClientRequest, ServerRequest,
+};
+
+pub const USAGE: &str = "Usage: fetris server_address [mode[:parameter]] [start_level]
+       fetris server_address rooms
+       fetris server_address create|create-private room_name size [mode[:parameter]] [start_level]
+       fetris server_address join code
+       fetris server_address pools
+       fetris server_address spectate pool_id";
+
+// What the player asked for on the command line
+pub enum Command {
+    // Without a mode, the server chooses it
+    Play(Option<GameMode>, GameOptions),
+    ListRooms,
+    CreateRoom(RoomSettings),
+    JoinRoom(u32),
+    ListPools,
+    Spectate(PoolId),
+}
+
+fn parse_mode(args: &[String]) -> Option<(Option<GameMode>, GameOptions)> {
+    if args.len() > 2 {
+        return None;
+    }
+    let mode = args.first().map(|mode| mode.parse()).transpose().ok()?;
+    let mut options = GameOptions::default();
+    if let Some(start_level) = args.get(1) {
+        options.start_level = start_level.parse().ok()?;
+    }
+    Some((mode, options))
+}
+
+// args without the program name and the server address
+pub fn parse_args(args: &[String]) -> Option<Command> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("rooms") if args.len() == 1 => Some(Command::ListRooms),
+        Some("join") if args.len() == 2 => args[1].parse().ok().map(Command::JoinRoom),
+        Some("pools") if args.len() == 1 => Some(Command::ListPools),
+        Some("spectate") if args.len() == 2 => args[1].parse().ok().map(Command::Spectate),
+        Some(create @ "create") | Some(create @ "create-private") if args.len() >= 3 => {
+            let (mode, options) = parse_mode(&args[3..])?;
+            Some(Command::CreateRoom(RoomSettings {
+                name: args[1].clone(),
+                size: args[2].parse().ok()?,
+                mode: mode.unwrap_or(GameMode::Versus),
+                options,
+                private: create == "create-private",
+            }))
+        }
+        _ => parse_mode(args).map(|(mode, options)| Command::Play(mode, options)),
+    }
+}
+
+fn print_room(room: &RoomInfos) {
+    print!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
+    println!(
+        "Room {} (code {}{})",
+        room.name,
+        room.code,
+        if room.private { ", private" } else { "" }
+    );
+    println!("Mode: {:?}", room.mode);
+    println!("Players {}/{}:", room.players.len(), room.size);
+    for player in room.players.iter() {
+        println!("  {}", player);
+    }
+    println!("Host: {}", room.host);
+}
+
+fn print_room_list(rooms: &[RoomInfos]) {
+    if rooms.is_empty() {
+        println!("No room open, create one!");
+    }
+    for room in rooms {
+        println!(
+            "{}  {:<20} {:?}  {}/{}  hosted by {}",
+            room.code,
+            room.name,
+            room.mode,
+            room.players.len(),
+            room.size,
+            room.host
+        );
+    }
+}
+
+fn print_pool_list(pools: &[PoolInfos]) {
+    if pools.is_empty() {
+        println!("No game is being played");
+    }
+    for pool in pools {
+        let players: Vec<_> = pool
+            .players
+            .iter()
+            .map(|player| player.name.as_str())
+            .collect();
+        println!("{}  {:?}  {}", pool.id, pool.mode, players.join(", "));
+    }
+}
+
+fn print_refusal(reason: BadRequestReason) {
+    println!("The server refused the request: {}", reason);
+}
+
+// Shows the room we are in until its match or our game from the queue starts
+fn wait_for_game(stream: &TcpStream) -> Option<(PlayerGame, GameSettings, SessionToken)> {
+    loop {
+        match ServerRequest::from_reader(stream) {
+            Ok(ServerRequest::GameReady(game, settings, token)) => {
+                return Some((game, settings, token))
+            }
+            Ok(ServerRequest::RoomUpdate(room)) => print_room(&room),
+            Ok(ServerRequest::BadRequest(reason)) => {
+                print_refusal(reason);
+                return None;
+            }
+            Ok(_) => {}
+            Err(_) => return None,
+        }
+    }
+}
+
+// Waits for our new room to be shown, false if the server refused to create it
+fn wait_for_room(stream: &TcpStream) -> bool {
+    loop {
+        match ServerRequest::from_reader(stream) {
+            Ok(ServerRequest::RoomUpdate(room)) => {
+                print_room(&room);
+                return true;
+            }
+            Ok(ServerRequest::BadRequest(reason)) => {
+                print_refusal(reason);
+                return false;
+            }
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+pub fn join_game(
+    stream: &mut TcpStream,
+    command: Command,
+) -> Result<Option<(PlayerGame, GameSettings, SessionToken)>, std::io::Error> {
+    match command {
+        Command::Play(mode, options) => {
+            stream.write_all(&ClientRequest::AskForAGame(mode, options).to_bytes()?)?;
+            println!(
+                "{}{}Waiting for other players ...",
+                termion::clear::All,
+                termion::cursor::Goto(1, 1),
+            );
+            Ok(wait_for_game(stream))
+        }
+        Command::ListRooms => {
+            stream.write_all(&ClientRequest::ListRooms.to_bytes()?)?;
+            loop {
+                match ServerRequest::from_reader(&*stream) {
+                    Ok(ServerRequest::RoomList(rooms)) => {
+                        print_room_list(&rooms);
+                        return Ok(None);
+                    }
+                    Ok(_) => {}
+                    Err(_) => return Ok(None),
+                }
+            }
+        }
+        Command::ListPools => {
+            stream.write_all(&ClientRequest::ListPools.to_bytes()?)?;
+            loop {
+                match ServerRequest::from_reader(&*stream) {
+                    Ok(ServerRequest::PoolList(pools)) => {
+                        print_pool_list(&pools);
+                        return Ok(None);
+                    }
+                    Ok(_) => {}
+                    Err(_) => return Ok(None),
+                }
+            }
+        }
+        Command::Spectate(_) => Ok(None),
+        Command::JoinRoom(code) => {
+            stream.write_all(&ClientRequest::JoinRoom(code).to_bytes()?)?;
+            Ok(wait_for_game(stream))
+        }
+        Command::CreateRoom(settings) => {
+            stream.write_all(&ClientRequest::CreateRoom(settings).to_bytes()?)?;
+            if !wait_for_room(stream) {
+                return Ok(None);
+            }
+            let reader = stream.try_clone()?;
+            let waiting = thread::spawn(move || wait_for_game(&reader));
+
+            // The room keeps being shown by the waiting thread until the host starts it
+            let mut line = String::new();
+            stdin().read_line(&mut line)?;
+            stream.write_all(&ClientRequest::StartRoom.to_bytes()?)?;
+            Ok(waiting.join().unwrap())
+        }
+    }
+}
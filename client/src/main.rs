@@ -1,9 +1,13 @@
-use fetris_protocol::{game::PlayerGame, ClientRequest, ServerRequest};
+use fetris_protocol::{
+    game::PlayerGame,
+    handshake::{self, ClientKind},
+    lock_delay::LockDelay,
+    ClientRequest,
+};
 use std::env;
 use std::io::{stdout, Write};
 use std::net::TcpStream;
 use std::sync::{Arc, Mutex};
-use std::time;
 use termion;
 use termion::raw::IntoRawMode;
 
@@ -11,11 +15,14 @@ mod client_server_showdown;
 mod config;
 mod fall_management;
 mod keyboard_listener;
+mod lobby;
 mod print;
 mod server_receiver;
+mod spectator;
 
 use client_server_showdown::ActionsQueues;
 use config::Config;
+use print::OtherPlayers;
 
 fn main() -> Result<(), std::io::Error> {
     let config = if let Some(config) = Config::from_path("config.toml") {
@@ -24,56 +31,80 @@ fn main() -> Result<(), std::io::Error> {
         Config::default()
     };
 
-    if env::args().len() != 2 {
-        println!("Usage: fetris server_address");
+    let args: Vec<String> = env::args().collect();
+    let command = if let Some(command) = args.get(2..).and_then(lobby::parse_args) {
+        command
+    } else {
+        println!("{}", lobby::USAGE);
         return Ok(());
-    }
+    };
 
-    let mut stream = TcpStream::connect(env::args().nth(1).unwrap())?;
+    let mut stream = TcpStream::connect(&args[1])?;
+    if let Err(err) = handshake::hello(&stream, ClientKind::Terminal) {
+        println!("{}", err);
+        return Ok(());
+    }
+    if let Some(name) = config.name() {
+        stream.write_all(&ClientRequest::SetName(name.to_string()).to_bytes()?)?;
+    }
 
     let _hide_cursor = termion::cursor::HideCursor::from(stdout());
 
     let game_board: Arc<Mutex<Option<PlayerGame>>> = Arc::new(Mutex::new(None));
     let action_queues: Arc<Mutex<ActionsQueues>> = Arc::new(Mutex::new(ActionsQueues::new()));
-    let last_action = Arc::new(Mutex::new(time::Instant::now()));
-
-    stream.write(&ClientRequest::AskForAGame.into_bytes())?;
+    let other_players: Arc<Mutex<OtherPlayers>> = Arc::new(Mutex::new(OtherPlayers::default()));
 
-    println!(
-        "{}{}Waiting for other players ...",
-        termion::clear::All,
-        termion::cursor::Goto(1, 1),
-    );
+    if let lobby::Command::Spectate(pool_id) = command {
+        return spectator::spectate(stream, pool_id);
+    }
 
-    let falling_interval = {
-        let (game, falling_interval) = if let Ok(ServerRequest::GameReady(game, update_time)) =
-            ServerRequest::from_reader(&stream)
-        {
-            (game, update_time)
+    let (lock_delay, mode, token) = {
+        let (game, settings, token) = if let Some(game) = lobby::join_game(&mut stream, command)? {
+            game
         } else {
-            panic!("Invalid first server request");
+            return Ok(());
         };
         let mut board = game_board.lock().unwrap();
         *board = Some(game);
-        falling_interval
+        (
+            Arc::new(Mutex::new(LockDelay::new(settings.lock_delay))),
+            settings.mode,
+            token,
+        )
     };
+    // Shared by the threads sending requests, the receiver replaces it when reconnecting
+    let writer = Arc::new(Mutex::new(stream.try_clone().unwrap()));
 
     let _stdout = stdout().into_raw_mode().unwrap();
-    print::launch_print_thread(game_board.clone(), action_queues.clone());
+    print::launch_print_thread(
+        game_board.clone(),
+        action_queues.clone(),
+        other_players.clone(),
+        mode,
+    );
     server_receiver::launch_server_receiver_thread(
-        stream.try_clone().unwrap(),
+        stream,
+        writer.clone(),
+        token,
         action_queues.clone(),
         game_board.clone(),
+        other_players.clone(),
     );
 
     fall_management::fall_management_thread(
-        stream.try_clone().unwrap(),
+        writer.clone(),
         action_queues.clone(),
-        last_action.clone(),
+        lock_delay.clone(),
         game_board.clone(),
-        falling_interval,
     );
 
-    keyboard_listener::keyboard_listen(stream, action_queues, config, game_board, last_action);
+    keyboard_listener::keyboard_listen(
+        writer,
+        action_queues,
+        config,
+        game_board,
+        lock_delay,
+        other_players,
+    );
     Ok(())
 }
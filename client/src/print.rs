@@ -5,12 +5,62 @@ use std::{
 use termion::color;
 
 use fetris_protocol::{
-    game::{Direction, PlayerGame, PlayerMinimalInfos},
+    actions,
+    game::{Direction, GameAction, PlayerGame, PlayerId, PlayerMinimalInfos},
+    game_mode::{GameMode, GameStats},
+    gravity::Gravity,
+    pool::MatchResult,
     tetrimino::TetriminoType,
 };
 
 use crate::client_server_showdown::ActionsQueues;
 
+const MAX_MINI_BOARDS: usize = 4;
+const MINI_BOARD_WIDTH: u16 = 13;
+
+// Players of the pool as last sent by the server, their boards, and the one we are attacking
+#[derive(Default)]
+pub struct OtherPlayers {
+    pub list: Vec<PlayerMinimalInfos>,
+    pub games: Vec<PlayerGame>,
+    pub target: Option<PlayerId>,
+}
+
+impl OtherPlayers {
+    pub fn apply_action(&mut self, id: PlayerId, action: GameAction) {
+        if let Some(game) = self.games.iter_mut().find(|game| game.id() == id) {
+            let _ = actions::apply_action(game, action);
+        }
+    }
+
+    // Their falls move as many rows as ours
+    pub fn set_gravity(&mut self, id: PlayerId, gravity: Gravity) {
+        if let Some(game) = self.games.iter_mut().find(|game| game.id() == id) {
+            game.set_gravity(gravity);
+        }
+    }
+
+    pub fn update_game(&mut self, snapshot: PlayerGame) {
+        match self
+            .games
+            .iter_mut()
+            .find(|game| game.id() == snapshot.id())
+        {
+            Some(game) => *game = snapshot,
+            None => {
+                self.games.push(snapshot);
+                self.games.sort_by_key(|game| game.id());
+            }
+        }
+    }
+
+    pub fn is_dead(&self, id: PlayerId) -> bool {
+        self.list
+            .iter()
+            .any(|player| player.id == id && player.dead)
+    }
+}
+
 fn print_tetrimino_at(tetrimino: TetriminoType, x: u16, y: u16) {
     for j in 0..2 {
         print!("{}  ", termion::cursor::Goto(x, j as u16 + y));
@@ -37,22 +87,261 @@ fn print_tetrimino_at(tetrimino: TetriminoType, x: u16, y: u16) {
     }
 }
 
-fn print_other_player(other_players: &[PlayerMinimalInfos], x: u16, y: u16) {
-    for i in 0..other_players.len() {
+fn print_other_player(
+    other_players: &[PlayerMinimalInfos],
+    own_id: PlayerId,
+    target: Option<PlayerId>,
+    x: u16,
+    y: u16,
+) {
+    let opponents = other_players.iter().filter(|player| player.id != own_id);
+    for (i, player) in opponents.enumerate() {
         print!(
-            "{}{}{}{}",
+            "{}{}{} {} ({} KO){}",
             termion::cursor::Goto(x, i as u16 * 2 + y),
-            if other_players[i].dead {
+            if player.dead {
                 color::Red.fg_str()
             } else {
                 color::White.fg_str()
             },
-            other_players[i].name,
+            if target == Some(player.id) { ">" } else { " " },
+            player.name,
+            player.kos,
             color::Fg(color::Reset),
         );
     }
 }
 
+// One character per cell, with the player name on top
+pub fn print_mini_board(game: &PlayerGame, dead: bool, x: u16, y: u16) {
+    let matrix = game.matrix();
+    let tetrimino = game.current_tetrimino();
+    let name: String = game
+        .name()
+        .chars()
+        .take(MINI_BOARD_WIDTH as usize - 2)
+        .collect();
+
+    print!(
+        "{}{}{}{}",
+        termion::cursor::Goto(x, y),
+        if dead {
+            color::Red.fg_str()
+        } else {
+            color::White.fg_str()
+        },
+        name,
+        color::Fg(color::Reset)
+    );
+    for j in 0..20 {
+        print!("{}|", termion::cursor::Goto(x, y + 1 + j));
+        let row = 19 - j as usize;
+        for (column, cell) in matrix[row].iter().enumerate() {
+            let ttype = cell.or_else(|| {
+                tetrimino
+                    .filter(|tetrimino| tetrimino.check_position(column as i8, row as i8))
+                    .map(|tetrimino| tetrimino.ttype())
+            });
+            if let Some(ttype) = ttype {
+                print!("{} {}", tetrimino_color(ttype), color::Bg(color::Reset));
+            } else {
+                print!(" ");
+            }
+        }
+        print!("|");
+    }
+    print!("{}‾‾‾‾‾‾‾‾‾‾‾‾", termion::cursor::Goto(x, y + 21));
+}
+
+// The target comes first, then the other opponents by id
+fn print_opponent_boards(other_players: &OtherPlayers, own_id: PlayerId, x: u16, y: u16) {
+    let mut opponents: Vec<_> = other_players
+        .games
+        .iter()
+        .filter(|game| game.id() != own_id)
+        .collect();
+    opponents.sort_by_key(|game| other_players.target != Some(game.id()));
+
+    for (i, game) in opponents.into_iter().take(MAX_MINI_BOARDS).enumerate() {
+        print_mini_board(
+            game,
+            other_players.is_dead(game.id()),
+            x + i as u16 * MINI_BOARD_WIDTH,
+            y,
+        );
+    }
+}
+
+fn print_score(game: &PlayerGame, x: u16, y: u16) {
+    let score = game.score();
+
+    print!("{}Score: {}", termion::cursor::Goto(x, y), score.score());
+    print!(
+        "{}Level: {}",
+        termion::cursor::Goto(x, y + 1),
+        score.level()
+    );
+    print!(
+        "{}Lines: {}",
+        termion::cursor::Goto(x, y + 2),
+        score.lines()
+    );
+    if let Some(combo) = score.combo().filter(|combo| *combo > 0) {
+        print!("{}Combo: {}", termion::cursor::Goto(x, y + 3), combo);
+    }
+    if score.back_to_back() {
+        print!("{}Back-to-Back", termion::cursor::Goto(x, y + 4));
+    }
+}
+
+// As m:ss.cc
+fn format_time(time_ms: u64) -> String {
+    format!(
+        "{}:{:02}.{:02}",
+        time_ms / 60000,
+        time_ms / 1000 % 60,
+        time_ms / 10 % 100
+    )
+}
+
+fn print_timer(game: &PlayerGame, mode: GameMode, elapsed: time::Duration, x: u16, y: u16) {
+    match mode {
+        GameMode::Sprint(lines) => print!(
+            "{}Time: {} ({} lines)",
+            termion::cursor::Goto(x, y),
+            format_time(elapsed.as_millis() as u64),
+            lines
+        ),
+        GameMode::Ultra(_) => {
+            let time_left = mode.time_limit().unwrap_or_default();
+            print!(
+                "{}Time left: {}",
+                termion::cursor::Goto(x, y),
+                format_time(time_left.saturating_sub(elapsed).as_millis() as u64)
+            );
+        }
+        GameMode::Survival => print!(
+            "{}Time: {}",
+            termion::cursor::Goto(x, y),
+            format_time(elapsed.as_millis() as u64)
+        ),
+        GameMode::Dig(_) => print!(
+            "{}Time: {} ({} rows left)",
+            termion::cursor::Goto(x, y),
+            format_time(elapsed.as_millis() as u64),
+            game.garbage_rows()
+        ),
+        GameMode::Versus | GameMode::Marathon(_) => {}
+    }
+}
+
+pub fn print_results(stats: &GameStats) {
+    let lines = [
+        format!("Time: {}", format_time(stats.time_ms)),
+        format!(
+            "Pieces: {} ({:.2}/s)",
+            stats.pieces,
+            stats.pieces_per_second()
+        ),
+        format!("Lines: {}", stats.lines),
+        format!("Score: {}", stats.score),
+        format!("Level: {}", stats.level),
+    ];
+
+    println!(
+        "{}---------------------------",
+        termion::cursor::Goto(1, 10)
+    );
+    println!(
+        "{}| {:<23} |",
+        termion::cursor::Goto(1, 11),
+        if stats.completed {
+            "Finished"
+        } else {
+            "Game Over"
+        }
+    );
+    for (i, line) in lines.iter().enumerate() {
+        println!(
+            "{}| {:<23} |",
+            termion::cursor::Goto(1, 12 + i as u16),
+            line
+        );
+    }
+    println!(
+        "{}---------------------------",
+        termion::cursor::Goto(1, 12 + lines.len() as u16)
+    );
+    print_rematch_hint(13 + lines.len() as u16);
+}
+
+pub fn print_match_result(result: &MatchResult) {
+    println!(
+        "{}---------------------------",
+        termion::cursor::Goto(1, 10)
+    );
+    println!("{}| {:<23} |", termion::cursor::Goto(1, 11), "Match over");
+    for (i, player) in result.placements.iter().enumerate() {
+        let line = format!("{}. {} ({} KO)", i + 1, player.name, player.kos);
+        println!(
+            "{}| {:<23} |",
+            termion::cursor::Goto(1, 12 + i as u16),
+            line
+        );
+    }
+    println!(
+        "{}---------------------------",
+        termion::cursor::Goto(1, 12 + result.placements.len() as u16)
+    );
+    print_rematch_hint(13 + result.placements.len() as u16);
+}
+
+fn print_rematch_hint(y: u16) {
+    println!("{}Press r for a rematch", termion::cursor::Goto(1, y));
+}
+
+pub fn print_rematch_votes(votes: u32, players: u32) {
+    println!(
+        "{}Rematch: {}/{}   ",
+        termion::cursor::Goto(1, 9),
+        votes,
+        players
+    );
+}
+
+pub fn print_pool_closed() {
+    println!(
+        "{}The pool closed, press Ctrl-C to quit",
+        termion::cursor::Goto(1, 9)
+    );
+}
+
+fn print_garbage_meter(game: &PlayerGame, x: u16, y: u16) {
+    let pending_garbage = game.pending_garbage().min(22) as u16;
+
+    for j in 0..pending_garbage {
+        print!(
+            "{}{} {}",
+            termion::cursor::Goto(x, y - j),
+            color::Red.bg_str(),
+            color::Bg(color::Reset)
+        );
+    }
+}
+
+pub fn tetrimino_color(ttype: TetriminoType) -> String {
+    match ttype {
+        TetriminoType::I => color::Cyan.bg_str().to_string(),
+        TetriminoType::J => color::Blue.bg_str().to_string(),
+        TetriminoType::L => color::Rgb(255, 173, 0).bg_string(),
+        TetriminoType::O => color::Yellow.bg_str().to_string(),
+        TetriminoType::S => color::Green.bg_str().to_string(),
+        TetriminoType::T => color::Magenta.bg_str().to_string(),
+        TetriminoType::Z => color::Red.bg_str().to_string(),
+        TetriminoType::None => color::White.bg_str().to_string(),
+    }
+}
+
 fn print_game(game: &PlayerGame) {
     let matrix = game.matrix();
     let tetrimino = game.current_tetrimino();
@@ -82,17 +371,7 @@ fn print_game(game: &PlayerGame) {
                     tetrimino.unwrap().ttype()
                 };
 
-                let color = match ttype {
-                    TetriminoType::I => color::Cyan.bg_str().to_string(),
-                    TetriminoType::J => color::Blue.bg_str().to_string(),
-                    TetriminoType::L => color::Rgb(255, 173, 0).bg_string(),
-                    TetriminoType::O => color::Yellow.bg_str().to_string(),
-                    TetriminoType::S => color::Green.bg_str().to_string(),
-                    TetriminoType::T => color::Magenta.bg_str().to_string(),
-                    TetriminoType::Z => color::Red.bg_str().to_string(),
-                    TetriminoType::None => color::White.bg_str().to_string(),
-                };
-                print!("{}  {}", color, color::Bg(color::Reset));
+                print!("{}  {}", tetrimino_color(ttype), color::Bg(color::Reset));
             } else if prediction.is_some() && prediction.unwrap().check_position(x as i8, y as i8) {
                 print!("{}  {}", color::Bg(color::White), color::Bg(color::Reset));
             } else {
@@ -111,26 +390,50 @@ fn print_game(game: &PlayerGame) {
 
         print_tetrimino_at(pending_tetriminos[j], 23, 8 + (3 * i as u16));
     }
+    print_garbage_meter(game, 22, 24);
+    print_score(game, 2, 26);
 }
 
-pub fn print_interface(game: &PlayerGame, other_players: &[PlayerMinimalInfos]) {
+pub fn print_interface(
+    game: &PlayerGame,
+    other_players: &OtherPlayers,
+    mode: GameMode,
+    elapsed: time::Duration,
+) {
     print!("{}{}", termion::cursor::Goto(1, 1), termion::clear::All,);
     print_game(&game);
-    print_other_player(&other_players, 40, 1);
+    print_timer(game, mode, elapsed, 2, 32);
+    print_opponent_boards(other_players, game.id(), 40, 1);
+    print_other_player(&other_players.list, game.id(), other_players.target, 40, 24);
     println!("");
 }
 
 pub fn launch_print_thread(
     board_mutex: Arc<Mutex<Option<PlayerGame>>>,
     action_queues: Arc<Mutex<ActionsQueues>>,
+    other_players: Arc<Mutex<OtherPlayers>>,
+    mode: GameMode,
 ) {
+    let mut started_at = time::Instant::now();
+    let mut playing = true;
     thread::spawn(move || loop {
         {
             let mut action_queues = action_queues.lock().unwrap();
             let board = board_mutex.lock().unwrap();
+            // A new board after the end of a match is a rematch, its timer starts over
+            if board.is_some() && !playing {
+                started_at = time::Instant::now();
+            }
+            playing = board.is_some();
             if let Some(board) = board.as_ref() {
                 let client_predicted_board = action_queues.client_board_prediction(board.clone());
-                print_interface(&client_predicted_board.0, &[]);
+                let other_players = other_players.lock().unwrap();
+                print_interface(
+                    &client_predicted_board.0,
+                    &other_players,
+                    mode,
+                    started_at.elapsed(),
+                );
             }
         }
         thread::sleep(time::Duration::from_millis(15));
@@ -1,19 +1,59 @@
 use std::{
-    net::TcpStream,
+    net::{SocketAddr, TcpStream},
     sync::{Arc, Mutex},
-    thread,
+    thread, time,
 };
 use termion;
 
-use fetris_protocol::{actions, game::PlayerGame, ServerRequest};
+use fetris_protocol::{
+    actions,
+    game::{PlayerGame, SessionToken},
+    handshake::{self, ClientKind},
+    ClientRequest, ServerRequest,
+};
+
+use crate::{
+    client_server_showdown::ActionsQueues,
+    print::{self, OtherPlayers},
+};
 
-use crate::client_server_showdown::ActionsQueues;
+const RECONNECT_ATTEMPTS: u32 = 30;
+
+// Tries for a while to open a new connection and resume the game on it
+fn reconnect(server_addr: SocketAddr, token: SessionToken) -> Option<TcpStream> {
+    for _ in 0..RECONNECT_ATTEMPTS {
+        thread::sleep(time::Duration::from_secs(1));
+        if let Ok(stream) = TcpStream::connect(server_addr) {
+            if handshake::hello(&stream, ClientKind::Terminal).is_ok()
+                && ClientRequest::Resume(token).write_to(&stream).is_ok()
+            {
+                return Some(stream);
+            }
+        }
+    }
+    None
+}
+
+// The server does not know our game anymore, there is nothing left to play
+fn resume_failed(game_board: &Mutex<Option<PlayerGame>>) {
+    *game_board.lock().unwrap() = None;
+    println!(
+        "{}Could not resume the game, press Ctrl-C to quit",
+        termion::cursor::Goto(1, 9)
+    );
+}
 
 pub fn launch_server_receiver_thread(
-    reader: TcpStream,
+    mut reader: TcpStream,
+    writer: Arc<Mutex<TcpStream>>,
+    mut token: SessionToken,
     action_queues: Arc<Mutex<ActionsQueues>>,
     game_board: Arc<Mutex<Option<PlayerGame>>>,
+    other_players: Arc<Mutex<OtherPlayers>>,
 ) {
+    let server_addr = reader.peer_addr().unwrap();
+    // A Resume was sent on a new connection, and the game did not come back yet
+    let mut resuming = false;
     thread::spawn(move || loop {
         if let Ok(request) = ServerRequest::from_reader(&reader) {
             if request == ServerRequest::GameOver {
@@ -31,8 +71,93 @@ pub fn launch_server_receiver_thread(
                     let _ = actions::apply_action(board.as_mut().unwrap(), action.clone());
                     action_queues.push_server_action(action);
                 }
+                // Our predictions went further than the server, they are dropped
+                ServerRequest::Resync(game) => {
+                    let mut action_queues = action_queues.lock().unwrap();
+                    let mut board = game_board.lock().unwrap();
+                    if board.is_some() {
+                        *board = Some(game);
+                        *action_queues = ActionsQueues::new();
+                    }
+                }
+                ServerRequest::GravityChanged(gravity) => {
+                    let mut board = game_board.lock().unwrap();
+                    if let Some(board) = board.as_mut() {
+                        board.set_gravity(gravity);
+                    }
+                }
+                ServerRequest::PendingGarbage(pending_garbage) => {
+                    let mut board = game_board.lock().unwrap();
+                    if let Some(board) = board.as_mut() {
+                        board.set_pending_garbage(pending_garbage);
+                    }
+                }
+                // A rematch, or our game sent back after reconnecting
+                ServerRequest::GameReady(game, _, new_token) => {
+                    token = new_token;
+                    resuming = false;
+                    let mut action_queues = action_queues.lock().unwrap();
+                    let mut board = game_board.lock().unwrap();
+                    let mut other_players = other_players.lock().unwrap();
+                    *action_queues = ActionsQueues::new();
+                    *board = Some(game);
+                    other_players.games.clear();
+                    other_players.target = None;
+                }
+                ServerRequest::MatchResult(result) => {
+                    let mut board = game_board.lock().unwrap();
+                    *board = None;
+
+                    print::print_match_result(&result);
+                }
+                ServerRequest::RematchVotes(votes, players) => {
+                    print::print_rematch_votes(votes, players);
+                }
+                ServerRequest::PoolClosed => print::print_pool_closed(),
+                ServerRequest::GameFinished(stats) => {
+                    let mut board = game_board.lock().unwrap();
+                    *board = None;
+
+                    print::print_results(&stats);
+                }
+                ServerRequest::PlayerListUpdate(list) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.list = list;
+                }
+                ServerRequest::PlayerAction(id, action) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.apply_action(id, action);
+                }
+                ServerRequest::PlayerSnapshot(snapshot) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.update_game(snapshot);
+                }
+                ServerRequest::PlayerGravityChanged(id, gravity) => {
+                    let mut other_players = other_players.lock().unwrap();
+                    other_players.set_gravity(id, gravity);
+                }
+                ServerRequest::BadRequest(_) if resuming => {
+                    resuming = false;
+                    resume_failed(&game_board);
+                }
                 _ => {}
             }
+        } else if resuming {
+            resume_failed(&game_board);
+            break;
+        } else if game_board.lock().unwrap().is_some() {
+            // The connection dropped in the middle of a game, it is resumed on a new one
+            match reconnect(server_addr, token) {
+                Some(stream) => {
+                    *writer.lock().unwrap() = stream.try_clone().unwrap();
+                    reader = stream;
+                    resuming = true;
+                }
+                None => {
+                    resume_failed(&game_board);
+                    break;
+                }
+            }
         } else {
             break;
         }
@@ -0,0 +1,68 @@
+use std::{
+    io::Write,
+    net::TcpStream,
+    sync::{Arc, Mutex},
+    thread, time,
+};
+
+use fetris_protocol::{pool::PoolId, ClientRequest, ServerRequest};
+
+use crate::print::{print_mini_board, OtherPlayers};
+
+const BOARD_WIDTH: u16 = 13;
+const BOARD_HEIGHT: u16 = 23;
+const BOARDS_PER_LINE: u16 = 6;
+
+fn print_spectated_pool(pool: &OtherPlayers) {
+    print!("{}{}", termion::cursor::Goto(1, 1), termion::clear::All);
+    for (i, game) in pool.games.iter().enumerate() {
+        let i = i as u16;
+
+        print_mini_board(
+            game,
+            pool.is_dead(game.id()),
+            1 + (i % BOARDS_PER_LINE) * BOARD_WIDTH,
+            1 + (i / BOARDS_PER_LINE) * BOARD_HEIGHT,
+        );
+    }
+    println!();
+}
+
+pub fn spectate(mut stream: TcpStream, pool_id: PoolId) -> Result<(), std::io::Error> {
+    stream.write_all(&ClientRequest::Spectate(pool_id).to_bytes()?)?;
+    let games = match ServerRequest::from_reader(&stream) {
+        Ok(ServerRequest::SpectateReady(games, _)) => games,
+        Ok(ServerRequest::BadRequest(reason)) => {
+            println!("Cannot spectate pool {}: {}", pool_id, reason);
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    let pool = Arc::new(Mutex::new(OtherPlayers {
+        games,
+        ..OtherPlayers::default()
+    }));
+    {
+        let pool = pool.clone();
+        thread::spawn(move || loop {
+            print_spectated_pool(&pool.lock().unwrap());
+            thread::sleep(time::Duration::from_millis(50));
+        });
+    }
+
+    while let Ok(request) = ServerRequest::from_reader(&stream) {
+        let mut pool = pool.lock().unwrap();
+        match request {
+            ServerRequest::PlayerAction(id, action) => pool.apply_action(id, action),
+            ServerRequest::PlayerSnapshot(snapshot) => pool.update_game(snapshot),
+            ServerRequest::PlayerGravityChanged(id, gravity) => pool.set_gravity(id, gravity),
+            ServerRequest::PlayerListUpdate(list) => pool.list = list,
+            // The players of the pool agreed to a rematch
+            ServerRequest::SpectateReady(games, _) => pool.games = games,
+            ServerRequest::PoolClosed => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -9,29 +9,28 @@ pub enum ApplyActionError {
 pub fn apply_action(player: &mut PlayerGame, action: GameAction) -> Result<(), ApplyActionError> {
     match action {
         GameAction::MoveCurrentTetrimino(Direction::FastDown) => {
-            let matrix = player.matrix().clone();
-            if let Some(tetrimino) = player.current_tetrimino_mut() {
-                while tetrimino.can_move_to(&matrix, Direction::Down) {
-                    tetrimino.apply_direction(Direction::Down);
-                }
-
-                player.place_current_tetrimino();
+            if player.hard_drop().is_some() {
                 Ok(())
             } else {
                 Err(ApplyActionError::InvalidActionNoResetTimer)
             }
         }
-        GameAction::MoveCurrentTetrimino(direction) => {
-            let matrix = player.matrix().clone();
-            if let Some(tetrimino) = player.current_tetrimino_mut() {
-                if tetrimino.can_move_to(&matrix, direction) {
-                    tetrimino.apply_direction(direction);
-                    Ok(())
-                } else {
-                    Err(ApplyActionError::InvalidActionResetTimer)
-                }
+        GameAction::MoveCurrentTetrimino(Direction::Down) => {
+            if player.current_tetrimino().is_none() {
+                Err(ApplyActionError::InvalidActionNoResetTimer)
+            } else if player.soft_drop() {
+                Ok(())
             } else {
+                Err(ApplyActionError::InvalidActionResetTimer)
+            }
+        }
+        GameAction::MoveCurrentTetrimino(direction) => {
+            if player.current_tetrimino().is_none() {
                 Err(ApplyActionError::InvalidActionNoResetTimer)
+            } else if player.move_current_tetrimino(direction) {
+                Ok(())
+            } else {
+                Err(ApplyActionError::InvalidActionResetTimer)
             }
         }
         GameAction::NewTetrimino(added_tetrimino) => {
@@ -43,15 +42,12 @@ pub fn apply_action(player: &mut PlayerGame, action: GameAction) -> Result<(), A
             Ok(())
         }
         GameAction::Rotate(direction) => {
-            let matrix = player.matrix().clone();
-            if let Some(tetrimino) = player.current_tetrimino_mut() {
-                if tetrimino.rotate(&matrix, direction) {
-                    Ok(())
-                } else {
-                    Err(ApplyActionError::InvalidActionResetTimer)
-                }
-            } else {
+            if player.current_tetrimino().is_none() {
                 Err(ApplyActionError::InvalidActionNoResetTimer)
+            } else if player.rotate_current_tetrimino(direction) {
+                Ok(())
+            } else {
+                Err(ApplyActionError::InvalidActionResetTimer)
             }
         }
         GameAction::StockTetrimino => {
@@ -69,13 +65,8 @@ pub fn apply_action(player: &mut PlayerGame, action: GameAction) -> Result<(), A
             Ok(())
         }
         GameAction::Fall => {
-            let matrix = player.matrix().clone();
-            if let Some(tetrimino) = player.current_tetrimino_mut() {
-                if tetrimino.can_move_to(&matrix, Direction::Down) {
-                    tetrimino.apply_direction(Direction::Down);
-                } else {
-                    player.place_current_tetrimino();
-                }
+            if player.current_tetrimino().is_some() {
+                player.fall();
                 Ok(())
             } else {
                 Err(ApplyActionError::InvalidActionResetTimer)
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    // The connection failed or closed in the middle of a frame
+    Io(io::Error),
+    // A frame announcing more bytes than any request can take
+    FrameTooLong(u32),
+    // The whole frame was read but it is not a request
+    Malformed(bincode::Error),
+    Encode(bincode::Error),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "connection error: {}", err),
+            Self::FrameTooLong(length) => write!(f, "frame of {} bytes is too long", length),
+            Self::Malformed(err) => write!(f, "malformed request: {}", err),
+            Self::Encode(err) => write!(f, "cannot encode the request: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<io::Error> for ProtocolError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ProtocolError> for io::Error {
+    fn from(err: ProtocolError) -> Self {
+        match err {
+            ProtocolError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+// Why the server refused a request, sent with BadRequest
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BadRequestReason {
+    Malformed,
+    // Hello is only sent once, at the start of the connection
+    UnexpectedHello,
+    // Already in a queue, a room, a pool or spectating
+    Busy,
+    NotInRoom,
+    NotInPool,
+    InvalidSettings,
+    UnknownRoom,
+    RoomFull,
+    NotHost,
+    UnknownPool,
+    InvalidTarget,
+    MatchNotOver,
+    UnknownSession,
+    // A versus match needs an opponent
+    NotEnoughPlayers,
+}
+
+impl fmt::Display for BadRequestReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Self::Malformed => "the request could not be read",
+            Self::UnexpectedHello => "the handshake was already done",
+            Self::Busy => "already in a game, a room or a queue",
+            Self::NotInRoom => "not in a room",
+            Self::NotInPool => "not in a game",
+            Self::InvalidSettings => "invalid game or room settings",
+            Self::UnknownRoom => "no room with this code",
+            Self::RoomFull => "the room is full",
+            Self::NotHost => "only the host can do this",
+            Self::UnknownPool => "no game with this id",
+            Self::InvalidTarget => "no opponent with this id",
+            Self::MatchNotOver => "the match is not over",
+            Self::UnknownSession => "no game to resume",
+            Self::NotEnoughPlayers => "not enough players to start the match",
+        };
+        write!(f, "{}", reason)
+    }
+}
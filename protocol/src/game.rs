@@ -1,31 +1,67 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    game_mode::{GameMode, GameOptions},
+    gravity::{Gravity, GravityCurve},
+    lock_delay::LockDelaySettings,
+    randomizer::RandomizerKind,
+    rng::Seed,
+    scoring::{LineClear, Score},
+    spin::{detect_spin, LastAction, Spin},
     tetrimino::{Tetrimino, TetriminoType},
     tetrimino_bag::TetriminoBag,
 };
 
 pub type Matrix = [[Option<TetriminoType>; 10]; 32];
 
+// Identifies a player inside its pool
+pub type PlayerId = u32;
+
+// Given with the game, lets a player take it back after losing its connection
+pub type SessionToken = u64;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PlayerMinimalInfos {
+    pub id: PlayerId,
     pub name: String,
     pub dead: bool,
+    pub kos: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GameSettings {
+    pub mode: GameMode,
+    pub gravity_curve: GravityCurve,
+    pub randomizer: RandomizerKind,
+    pub lines_per_level: u32,
+    pub lock_delay: LockDelaySettings,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PlayerGame {
+    id: PlayerId,
     name: String,
     matrix: Matrix,
     current_tetrimino: Option<Tetrimino>,
     stocked_tetrimino: TetriminoType,
     pending_tetriminos: Vec<TetriminoType>,
     bag: TetriminoBag,
+    score: Score,
+    gravity: Gravity,
+    last_action: LastAction,
+    pending_garbage: u32,
+    pieces: u32,
 }
 
 impl PlayerGame {
-    pub fn new(name: String) -> Self {
-        let mut bag = TetriminoBag::new();
+    pub fn new(
+        id: PlayerId,
+        name: String,
+        seed: Seed,
+        settings: &GameSettings,
+        options: &GameOptions,
+    ) -> Self {
+        let mut bag = TetriminoBag::new(seed, settings.randomizer);
         let pending_tetriminos = vec![
             bag.choose_a_tetrimino(),
             bag.choose_a_tetrimino(),
@@ -35,15 +71,25 @@ impl PlayerGame {
             bag.choose_a_tetrimino(),
         ];
         Self {
+            id,
             name,
             matrix: [[None; 10]; 32],
             current_tetrimino: None,
             stocked_tetrimino: TetriminoType::None,
             pending_tetriminos,
             bag,
+            score: Score::new(options.start_level, settings.lines_per_level),
+            gravity: settings.gravity_curve.gravity(options.start_level),
+            last_action: LastAction::Move,
+            pending_garbage: 0,
+            pieces: 0,
         }
     }
 
+    pub fn id(&self) -> PlayerId {
+        self.id
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -63,6 +109,32 @@ impl PlayerGame {
         self.pending_tetriminos.clone()
     }
 
+    pub fn score(&self) -> &Score {
+        &self.score
+    }
+
+    // Number of tetriminos placed since the start of the game
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    pub fn gravity(&self) -> Gravity {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Gravity) {
+        self.gravity = gravity;
+    }
+
+    // Garbage lines waiting to be inserted on the next non-clearing placement
+    pub fn pending_garbage(&self) -> u32 {
+        self.pending_garbage
+    }
+
+    pub fn set_pending_garbage(&mut self, pending_garbage: u32) {
+        self.pending_garbage = pending_garbage;
+    }
+
     pub fn stock_current_tetrimino(&mut self) {
         if let Some(current_tetrimino) = self.current_tetrimino {
             let tmp_tetrimino = self.stocked_tetrimino;
@@ -76,6 +148,7 @@ impl PlayerGame {
     }
 
     pub fn change_current_tetrimino(&mut self, ttype: TetriminoType) {
+        self.last_action = LastAction::Move;
         if ttype == TetriminoType::None {
             self.current_tetrimino = None;
         } else {
@@ -107,6 +180,28 @@ impl PlayerGame {
         true
     }
 
+    // Number of rows from the floor to the highest block
+    pub fn stack_height(&self) -> u32 {
+        self.matrix
+            .iter()
+            .rposition(|row| row.iter().any(|x| x.is_some()))
+            .map_or(0, |y| y as u32 + 1)
+    }
+
+    // Number of rows still holding garbage blocks
+    pub fn garbage_rows(&self) -> u32 {
+        self.matrix
+            .iter()
+            .filter(|row| row.contains(&Some(TetriminoType::None)))
+            .count() as u32
+    }
+
+    pub fn is_matrix_empty(&self) -> bool {
+        self.matrix
+            .iter()
+            .all(|row| row.iter().all(|x| x.is_none()))
+    }
+
     pub fn remove_complete_lines(&mut self) -> Vec<u8> {
         let mut line_to_remove = Vec::new();
         for y in 0..self.matrix.len() {
@@ -125,10 +220,85 @@ impl PlayerGame {
         line_to_remove
     }
 
-    pub fn place_current_tetrimino(&mut self) -> Vec<u8> {
+    pub fn move_current_tetrimino(&mut self, direction: Direction) -> bool {
+        let matrix = self.matrix;
+        if let Some(tetrimino) = self.current_tetrimino.as_mut() {
+            if tetrimino.can_move_to(&matrix, direction) {
+                tetrimino.apply_direction(direction);
+                self.last_action = LastAction::Move;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn rotate_current_tetrimino(&mut self, revert_direction: bool) -> bool {
+        let matrix = self.matrix;
+        if let Some(tetrimino) = self.current_tetrimino.as_mut() {
+            if let Some(kick) = tetrimino.rotate_with_kick(&matrix, revert_direction) {
+                self.last_action = LastAction::Rotation(kick);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn soft_drop(&mut self) -> bool {
+        let matrix = self.matrix;
+        if let Some(tetrimino) = self.current_tetrimino.as_mut() {
+            if tetrimino.can_move_to(&matrix, Direction::Down) {
+                tetrimino.apply_direction(Direction::Down);
+                self.score.add_soft_drop(1);
+                self.last_action = LastAction::Move;
+                return true;
+            }
+        }
+        false
+    }
+
+    // Moves the current tetrimino down by the gravity, places it if it is already on the ground
+    pub fn fall(&mut self) -> Option<LineClear> {
+        let matrix = self.matrix;
+        if let Some(tetrimino) = self.current_tetrimino.as_mut() {
+            if !tetrimino.can_move_to(&matrix, Direction::Down) {
+                return Some(self.place_current_tetrimino());
+            }
+            for _ in 0..self.gravity.rows_per_fall {
+                if !tetrimino.can_move_to(&matrix, Direction::Down) {
+                    break;
+                }
+                tetrimino.apply_direction(Direction::Down);
+            }
+            self.last_action = LastAction::Move;
+        }
+        None
+    }
+
+    pub fn hard_drop(&mut self) -> Option<LineClear> {
+        let matrix = self.matrix;
+        if let Some(tetrimino) = self.current_tetrimino.as_mut() {
+            let mut rows = 0;
+            while tetrimino.can_move_to(&matrix, Direction::Down) {
+                tetrimino.apply_direction(Direction::Down);
+                rows += 1;
+            }
+            if rows > 0 {
+                self.last_action = LastAction::Move;
+            }
+            self.score.add_hard_drop(rows);
+            Some(self.place_current_tetrimino())
+        } else {
+            None
+        }
+    }
+
+    pub fn place_current_tetrimino(&mut self) -> LineClear {
+        let mut spin = Spin::None;
         if let Some(tetrimino) = self.current_tetrimino {
             let tetri_shape = tetrimino.to_blocks();
 
+            spin = detect_spin(&tetrimino, &self.matrix, self.last_action);
+
             for x in 0..tetri_shape.len() {
                 for y in 0..tetri_shape.len() {
                     let position = tetrimino.position();
@@ -141,8 +311,23 @@ impl PlayerGame {
                 }
             }
         }
+        let placed = self.current_tetrimino.is_some();
         self.current_tetrimino = None;
-        self.remove_complete_lines()
+        let rows = self.remove_complete_lines();
+        let mut clear = LineClear {
+            perfect_clear: !rows.is_empty() && self.is_matrix_empty(),
+            rows,
+            spin,
+            combo: None,
+            back_to_back: false,
+        };
+        if placed {
+            self.pieces += 1;
+            clear.back_to_back = clear.is_difficult() && self.score.back_to_back();
+            self.score.add_clear(&clear);
+            clear.combo = self.score.combo();
+        }
+        clear
     }
 
     pub fn add_garbage(&mut self, hole: usize) {
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::game::PlayerGame;
+
+pub const DEFAULT_SPRINT_LINES: u32 = 40;
+pub const DEFAULT_ULTRA_SECONDS: u32 = 120;
+pub const DEFAULT_MARATHON_LEVELS: u32 = 15;
+pub const MAX_START_LEVEL: u32 = 20;
+pub const DEFAULT_DIG_ROWS: u32 = 10;
+pub const MAX_DIG_ROWS: u32 = 20;
+pub const MAX_ULTRA_SECONDS: u32 = 600;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    // Last player standing, attacking each other with garbage
+    Versus,
+    // Clear this many lines as fast as possible
+    Sprint(u32),
+    // Score as much as possible in this many seconds
+    Ultra(u32),
+    // Clear levels up to this one, or forever
+    Marathon(Option<u32>),
+    // Start over this many rows of garbage and clear them all as fast as possible
+    Dig(u32),
+    // Last as long as possible under garbage rising faster and faster
+    Survival,
+}
+
+// Chosen by each player when asking for a game
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameOptions {
+    pub start_level: u32,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        Self { start_level: 1 }
+    }
+}
+
+impl GameMode {
+    // Solo modes start as soon as the player asks for a game
+    pub fn is_solo(self) -> bool {
+        self != Self::Versus
+    }
+
+    pub fn goal_reached(self, game: &PlayerGame) -> bool {
+        match self {
+            Self::Versus => false,
+            Self::Sprint(lines) => game.score().lines() >= lines,
+            Self::Ultra(_) => false,
+            Self::Marathon(Some(level_cap)) => game.score().level() > level_cap,
+            Self::Marathon(None) => false,
+            Self::Dig(_) => game.garbage_rows() == 0,
+            Self::Survival => false,
+        }
+    }
+
+    // The mode and options sent by a client are something we can play
+    pub fn accepts(self, options: &GameOptions) -> bool {
+        let valid_mode = match self {
+            Self::Sprint(lines) => lines >= 1,
+            Self::Ultra(seconds) => (1..=MAX_ULTRA_SECONDS).contains(&seconds),
+            Self::Marathon(Some(level_cap)) => level_cap >= 1,
+            Self::Dig(rows) => (1..=MAX_DIG_ROWS).contains(&rows),
+            _ => true,
+        };
+        let max_start_level = match self {
+            Self::Marathon(Some(level_cap)) => level_cap.min(MAX_START_LEVEL),
+            _ => MAX_START_LEVEL,
+        };
+        valid_mode && options.start_level >= 1 && options.start_level <= max_start_level
+    }
+
+    // The game ends for everyone still playing once it is elapsed
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            Self::Ultra(seconds) => Some(Duration::from_secs(seconds.into())),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = ();
+
+    // A mode name, optionally followed by its parameter: "sprint:20", "marathon:0" for endless
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, parameter) = match s.split_once(':') {
+            Some((name, parameter)) => (name, Some(parameter.parse().map_err(|_| ())?)),
+            None => (s, None),
+        };
+        match (name, parameter) {
+            ("versus", None) => Ok(Self::Versus),
+            ("sprint", lines) => Ok(Self::Sprint(lines.unwrap_or(DEFAULT_SPRINT_LINES))),
+            ("ultra", seconds) => Ok(Self::Ultra(seconds.unwrap_or(DEFAULT_ULTRA_SECONDS))),
+            ("marathon", level_cap) => Ok(Self::Marathon(
+                Some(level_cap.unwrap_or(DEFAULT_MARATHON_LEVELS)).filter(|l| *l > 0),
+            )),
+            ("dig", rows) => Ok(Self::Dig(rows.unwrap_or(DEFAULT_DIG_ROWS))),
+            ("survival", None) => Ok(Self::Survival),
+            _ => Err(()),
+        }
+    }
+}
+
+// Results of a finished game, timed by the server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GameStats {
+    // The goal of the mode was reached, the player did not top out
+    pub completed: bool,
+    pub time_ms: u64,
+    pub pieces: u32,
+    pub lines: u32,
+    pub score: u32,
+    pub level: u32,
+}
+
+impl GameStats {
+    pub fn new(game: &PlayerGame, time: Duration, completed: bool) -> Self {
+        Self {
+            completed,
+            time_ms: time.as_millis() as u64,
+            pieces: game.pieces(),
+            lines: game.score().lines(),
+            score: game.score().score(),
+            level: game.score().level(),
+        }
+    }
+
+    pub fn pieces_per_second(&self) -> f64 {
+        if self.time_ms == 0 {
+            0.
+        } else {
+            self.pieces as f64 * 1000. / self.time_ms as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_are_read_with_their_defaults() {
+        assert_eq!("versus".parse(), Ok(GameMode::Versus));
+        assert_eq!("sprint".parse(), Ok(GameMode::Sprint(DEFAULT_SPRINT_LINES)));
+        assert_eq!("ultra".parse(), Ok(GameMode::Ultra(DEFAULT_ULTRA_SECONDS)));
+        assert_eq!(
+            "marathon".parse(),
+            Ok(GameMode::Marathon(Some(DEFAULT_MARATHON_LEVELS)))
+        );
+        assert_eq!("dig".parse(), Ok(GameMode::Dig(DEFAULT_DIG_ROWS)));
+        assert_eq!("survival".parse(), Ok(GameMode::Survival));
+    }
+
+    #[test]
+    fn modes_are_read_with_their_parameter() {
+        assert_eq!("sprint:20".parse(), Ok(GameMode::Sprint(20)));
+        assert_eq!("ultra:180".parse(), Ok(GameMode::Ultra(180)));
+        assert_eq!("marathon:10".parse(), Ok(GameMode::Marathon(Some(10))));
+        assert_eq!("marathon:0".parse(), Ok(GameMode::Marathon(None)));
+        assert_eq!("dig:5".parse(), Ok(GameMode::Dig(5)));
+    }
+
+    #[test]
+    fn bad_modes_are_refused() {
+        for mode in [
+            "",
+            "tetris",
+            "versus:2",
+            "survival:10",
+            "sprint:",
+            "sprint:-1",
+            "sprint:forty",
+            "dig:5:5",
+        ] {
+            assert_eq!(mode.parse::<GameMode>(), Err(()), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn out_of_range_parameters_are_not_accepted() {
+        let options = GameOptions::default();
+        let accepts = |mode: &str| mode.parse::<GameMode>().unwrap().accepts(&options);
+
+        assert!(accepts("sprint:1"));
+        assert!(!accepts("sprint:0"));
+        assert!(!accepts("ultra:0"));
+        assert!(!accepts("ultra:601"));
+        assert!(!accepts("dig:0"));
+        assert!(!accepts("dig:21"));
+        assert!(accepts("marathon:0"));
+
+        let level = |start_level| GameOptions { start_level };
+        assert!(!GameMode::Versus.accepts(&level(0)));
+        assert!(GameMode::Versus.accepts(&level(MAX_START_LEVEL)));
+        assert!(!GameMode::Versus.accepts(&level(MAX_START_LEVEL + 1)));
+        assert!(GameMode::Marathon(Some(10)).accepts(&level(10)));
+        assert!(!GameMode::Marathon(Some(10)).accepts(&level(11)));
+    }
+}
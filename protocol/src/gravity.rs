@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Nobody polls faster than a frame, faster gravities move several rows per fall
+pub const MIN_CALL_EVERY_MS: u16 = 16;
+pub const MAX_ROWS_PER_FALL: u8 = 20;
+
+const NES_FRAMES_PER_ROW: [u8; 29] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
+const NES_FRAME_MS: f64 = 1000. / 60.0988;
+// The fixed speed Fetris had before the gravity curves
+const CLASSIC_MS_PER_ROW: f64 = 200.;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gravity {
+    pub call_every_ms: u16,
+    pub rows_per_fall: u8,
+}
+
+impl Gravity {
+    fn from_ms_per_row(ms_per_row: f64) -> Self {
+        if ms_per_row >= MIN_CALL_EVERY_MS as f64 {
+            Self {
+                call_every_ms: ms_per_row.round() as u16,
+                rows_per_fall: 1,
+            }
+        } else {
+            let rows = (MIN_CALL_EVERY_MS as f64 / ms_per_row).ceil();
+            Self {
+                call_every_ms: MIN_CALL_EVERY_MS,
+                rows_per_fall: rows.min(MAX_ROWS_PER_FALL as f64) as u8,
+            }
+        }
+    }
+
+    pub fn is_20g(&self) -> bool {
+        self.rows_per_fall >= MAX_ROWS_PER_FALL
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GravityCurve {
+    // The guideline curve, never slower than the classic 200ms per row
+    Classic,
+    Guideline,
+    Nes,
+}
+
+// https://tetris.wiki/Marathon
+fn guideline_ms_per_row(level: u32) -> f64 {
+    let n = (level - 1) as f64;
+    (0.8 - n * 0.007).powf(n) * 1000.
+}
+
+impl GravityCurve {
+    // Levels start at 1
+    pub fn gravity(self, level: u32) -> Gravity {
+        let level = level.max(1);
+        match self {
+            // 20G from level 20
+            Self::Classic | Self::Guideline if level >= 20 => Gravity {
+                call_every_ms: MIN_CALL_EVERY_MS,
+                rows_per_fall: MAX_ROWS_PER_FALL,
+            },
+            Self::Classic => {
+                Gravity::from_ms_per_row(guideline_ms_per_row(level).min(CLASSIC_MS_PER_ROW))
+            }
+            Self::Guideline => Gravity::from_ms_per_row(guideline_ms_per_row(level)),
+            Self::Nes => {
+                let frames = NES_FRAMES_PER_ROW
+                    .get(level as usize - 1)
+                    .copied()
+                    .unwrap_or(1);
+                Gravity::from_ms_per_row(frames as f64 * NES_FRAME_MS)
+            }
+        }
+    }
+}
+
+impl FromStr for GravityCurve {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Self::Classic),
+            "guideline" => Ok(Self::Guideline),
+            "nes" => Ok(Self::Nes),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classic_keeps_the_original_speed_at_first() {
+        for level in 1..=6 {
+            assert_eq!(
+                GravityCurve::Classic.gravity(level),
+                Gravity {
+                    call_every_ms: 200,
+                    rows_per_fall: 1,
+                },
+                "level {}",
+                level
+            );
+        }
+        assert!(GravityCurve::Classic.gravity(7).call_every_ms < 200);
+        assert_eq!(
+            GravityCurve::Classic.gravity(12),
+            GravityCurve::Guideline.gravity(12)
+        );
+    }
+
+    #[test]
+    fn curves_speed_up_with_the_level() {
+        for curve in [
+            GravityCurve::Classic,
+            GravityCurve::Guideline,
+            GravityCurve::Nes,
+        ] {
+            let mut previous = curve.gravity(1);
+            for level in 2..=30 {
+                let gravity = curve.gravity(level);
+                assert!(
+                    gravity.call_every_ms <= previous.call_every_ms
+                        && gravity.rows_per_fall >= previous.rows_per_fall,
+                    "{:?} slows down at level {}",
+                    curve,
+                    level
+                );
+                previous = gravity;
+            }
+        }
+    }
+
+    #[test]
+    fn guideline_curves_reach_20g() {
+        for curve in [GravityCurve::Classic, GravityCurve::Guideline] {
+            assert!(!curve.gravity(15).is_20g());
+            assert!(curve.gravity(20).is_20g());
+            assert!(curve.gravity(30).is_20g());
+        }
+        assert_eq!(GravityCurve::Guideline.gravity(1).call_every_ms, 1000);
+        assert_eq!(GravityCurve::Nes.gravity(1).call_every_ms, 799);
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::{ClientRequest, ProtocolError, ServerRequest};
+
+// Bumped on every change of the requests, client and server must agree on it
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// How long to wait for the answer to our Hello, an older server never sends one
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Part of the Hello, new kinds go at the end to keep the handshake bytes
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ClientKind {
+    Terminal,
+    Ai,
+    WebProxy,
+}
+
+#[derive(Debug)]
+pub enum HandshakeError {
+    VersionMismatch(u32),
+    NoAnswer(ProtocolError),
+    UnexpectedAnswer,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::VersionMismatch(server_version) => write!(
+                f,
+                "The server speaks the protocol version {}, this client the version {}",
+                server_version, PROTOCOL_VERSION
+            ),
+            Self::NoAnswer(err) => write!(
+                f,
+                "The server did not answer the handshake, it may be too old for this client ({})",
+                err
+            ),
+            Self::UnexpectedAnswer => {
+                write!(f, "The server did not answer the handshake with a Hello")
+            }
+        }
+    }
+}
+
+// Sent first on every connection, before any other request
+pub fn hello(stream: &TcpStream, client_kind: ClientKind) -> Result<(), HandshakeError> {
+    let request = ClientRequest::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        client_kind,
+    };
+    request.write_to(stream).map_err(HandshakeError::NoAnswer)?;
+
+    let _ = stream.set_read_timeout(Some(HELLO_TIMEOUT));
+    let answer = ServerRequest::from_reader(stream);
+    let _ = stream.set_read_timeout(None);
+    match answer {
+        Ok(ServerRequest::Hello { .. }) => Ok(()),
+        Ok(ServerRequest::VersionMismatch { server_version }) => {
+            Err(HandshakeError::VersionMismatch(server_version))
+        }
+        Ok(_) => Err(HandshakeError::UnexpectedAnswer),
+        Err(err) => Err(HandshakeError::NoAnswer(err)),
+    }
+}
@@ -1,55 +1,261 @@
-use serde::{Deserialize, Serialize};
-use std::io::Read;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{Read, Write};
 
 pub mod actions;
+pub mod error;
 pub mod game;
+pub mod game_mode;
+pub mod gravity;
+pub mod handshake;
+pub mod lock_delay;
+pub mod pool;
+pub mod randomizer;
+pub mod rng;
+pub mod room;
 pub mod rotation_tetrimino;
+pub mod scoring;
+pub mod spin;
 pub mod tetrimino;
 pub mod tetrimino_bag;
 
-pub type DeserializeError = bincode::Error;
+pub use error::{BadRequestReason, ProtocolError};
+
+// Bigger frames are refused before reading them, they can only come from a broken peer
+const MAX_FRAME_LENGTH: u32 = 1 << 20;
+
+// Every request goes on the wire as its length, a big endian u32, then its bincode encoding
+fn into_frame<T: Serialize>(request: &T) -> Result<Vec<u8>, ProtocolError> {
+    let payload = bincode::serialize(request).map_err(ProtocolError::Encode)?;
+    if payload.len() > MAX_FRAME_LENGTH as usize {
+        return Err(ProtocolError::FrameTooLong(payload.len() as u32));
+    }
+    let mut frame = (payload.len() as u32).to_be_bytes().to_vec();
+
+    frame.extend(payload);
+    Ok(frame)
+}
+
+fn from_frame<T: DeserializeOwned, R: Read>(mut reader: R) -> Result<T, ProtocolError> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length);
+    if length > MAX_FRAME_LENGTH {
+        return Err(ProtocolError::FrameTooLong(length));
+    }
+
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload)?;
+    bincode::deserialize(&payload).map_err(ProtocolError::Malformed)
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientRequest {
+    // First request of every connection, the server closes it on another protocol version.
+    // It must stay the variant 0 for every version to read it, new requests go at the end
+    Hello {
+        protocol_version: u32,
+        client_kind: handshake::ClientKind,
+    },
     SetName(String),
-    AskForAGame,
+    // Without a mode, the default one of the server is played
+    AskForAGame(Option<game_mode::GameMode>, game_mode::GameOptions),
     Input(game::Input),
+    SetTarget(game::PlayerId),
+    CreateRoom(room::RoomSettings),
+    JoinRoom(room::RoomCode),
+    ListRooms,
+    LeaveRoom,
+    // Only the host of a room can start its match
+    StartRoom,
+    ListPools,
+    Spectate(pool::PoolId),
+    // Once the match ended, asks to play again with the same players
+    Rematch,
+    // Takes back a game left by a dropped connection, answered by a GameReady
+    Resume(game::SessionToken),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum ServerRequest {
-    BadRequest,
-    GameReady(game::PlayerGame, u16),
+    // Answers the Hello of a client speaking the same protocol version. Hello and
+    // VersionMismatch must stay the variants 0 and 1 for every version to read them
+    Hello { protocol_version: u32 },
+    VersionMismatch { server_version: u32 },
+    BadRequest(error::BadRequestReason),
+    GameReady(game::PlayerGame, game::GameSettings, game::SessionToken),
     PlayerListUpdate(Vec<game::PlayerMinimalInfos>),
     MinifiedAction(game::GameAction),
+    GravityChanged(gravity::Gravity),
+    PendingGarbage(u32),
     GameOver,
+    GameFinished(game_mode::GameStats),
+    RoomList(Vec<room::RoomInfos>),
+    RoomUpdate(room::RoomInfos),
+    PoolList(Vec<pool::PoolInfos>),
+    // Every player of the spectated pool, then their actions and snapshots as they play
+    SpectateReady(Vec<game::PlayerGame>, game::GameSettings),
+    PlayerAction(game::PlayerId, game::GameAction),
+    PlayerSnapshot(game::PlayerGame),
+    MatchResult(pool::MatchResult),
+    // Players who voted for a rematch, out of the players still in the pool
+    RematchVotes(u32, u32),
+    // Nobody asked for a rematch in time, the players are out of the pool
+    PoolClosed,
     Message(String, String),
+    // The board of the player as the server has it, after an input it refused
+    Resync(game::PlayerGame),
+    // The gravity of another player, its board only changes through its actions
+    PlayerGravityChanged(game::PlayerId, gravity::Gravity),
 }
 
 impl ClientRequest {
-    pub fn into_bytes(self) -> Vec<u8> {
-        bincode::serialize(&self).unwrap()
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProtocolError> {
+        into_frame(self)
+    }
+
+    // Writes the whole frame, a partial one would desync the stream
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), ProtocolError> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
     }
 
-    pub fn from_bytes(encoded: &[u8]) -> Self {
-        bincode::deserialize(encoded).unwrap()
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, ProtocolError> {
+        from_frame(encoded)
     }
 
-    pub fn from_reader<R: Read>(reader: R) -> Result<Self, DeserializeError> {
-        bincode::deserialize_from(reader)
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, ProtocolError> {
+        from_frame(reader)
     }
 }
 
 impl ServerRequest {
-    pub fn into_bytes(self) -> Vec<u8> {
-        bincode::serialize(&self).unwrap()
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ProtocolError> {
+        into_frame(self)
+    }
+
+    // Writes the whole frame, a partial one would desync the stream
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), ProtocolError> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, ProtocolError> {
+        from_frame(encoded)
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, ProtocolError> {
+        from_frame(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handshake::{ClientKind, PROTOCOL_VERSION};
+
+    // Clients and servers of every protocol version must agree on these bytes
+    #[test]
+    fn handshake_bytes_are_pinned() {
+        let hello = ClientRequest::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_kind: ClientKind::Terminal,
+        };
+        let bytes = hello.to_bytes().unwrap();
+        assert_eq!(bytes, [0, 0, 0, 12, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        match ClientRequest::from_bytes(&bytes).unwrap() {
+            ClientRequest::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                client_kind: ClientKind::Terminal,
+            } => {}
+            request => panic!("Unexpected request {:?}", request),
+        }
+
+        let hello = ServerRequest::Hello {
+            protocol_version: PROTOCOL_VERSION,
+        };
+        let bytes = hello.to_bytes().unwrap();
+        assert_eq!(bytes, [0, 0, 0, 8, 0, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(ServerRequest::from_bytes(&bytes).unwrap(), hello);
+
+        let mismatch = ServerRequest::VersionMismatch {
+            server_version: PROTOCOL_VERSION,
+        };
+        let bytes = mismatch.to_bytes().unwrap();
+        assert_eq!(bytes, [0, 0, 0, 8, 1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(ServerRequest::from_bytes(&bytes).unwrap(), mismatch);
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let requests = [
+            ServerRequest::BadRequest(BadRequestReason::RoomFull),
+            ServerRequest::Message("name".to_string(), "hello".to_string()),
+            ServerRequest::PendingGarbage(4),
+        ];
+        let mut stream = Vec::new();
+        for request in requests.iter() {
+            stream.extend(request.to_bytes().unwrap());
+        }
+
+        // Frames read one after the other from the same stream
+        let mut reader = &stream[..];
+        for request in requests.iter() {
+            assert_eq!(&ServerRequest::from_reader(&mut reader).unwrap(), request);
+        }
+        assert!(reader.is_empty());
+
+        let bytes = ClientRequest::SetName("name".to_string())
+            .to_bytes()
+            .unwrap();
+        match ClientRequest::from_bytes(&bytes).unwrap() {
+            ClientRequest::SetName(name) if name == "name" => {}
+            request => panic!("Unexpected request {:?}", request),
+        }
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut bytes = (MAX_FRAME_LENGTH + 1).to_be_bytes().to_vec();
+        bytes.extend(vec![0; 16]);
+        match ServerRequest::from_bytes(&bytes) {
+            Err(ProtocolError::FrameTooLong(length)) => assert_eq!(length, MAX_FRAME_LENGTH + 1),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let name = "a".repeat(MAX_FRAME_LENGTH as usize);
+        match ClientRequest::SetName(name).to_bytes() {
+            Err(ProtocolError::FrameTooLong(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
     }
 
-    pub fn from_bytes(encoded: &[u8]) -> Self {
-        bincode::deserialize(encoded).unwrap()
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let bytes = ServerRequest::PendingGarbage(4).to_bytes().unwrap();
+
+        for length in 0..bytes.len() {
+            match ServerRequest::from_bytes(&bytes[..length]) {
+                Err(ProtocolError::Io(err)) => {
+                    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof)
+                }
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
     }
 
-    pub fn from_reader<R: Read>(reader: R) -> Result<Self, DeserializeError> {
-        bincode::deserialize_from(reader)
+    #[test]
+    fn malformed_frames_keep_the_stream_in_sync() {
+        let mut stream = vec![0, 0, 0, 4, 255, 255, 255, 255];
+        stream.extend(ServerRequest::PendingGarbage(4).to_bytes().unwrap());
+
+        let mut reader = &stream[..];
+        match ServerRequest::from_reader(&mut reader) {
+            Err(ProtocolError::Malformed(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(
+            ServerRequest::from_reader(&mut reader).unwrap(),
+            ServerRequest::PendingGarbage(4)
+        );
     }
 }
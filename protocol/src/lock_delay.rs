@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::game::{Direction, PlayerGame};
+use crate::tetrimino::Tetrimino;
+
+pub const DEFAULT_LOCK_DELAY_MS: u16 = 500;
+pub const DEFAULT_MAX_LOCK_RESETS: u8 = 15;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LockDelaySettings {
+    pub delay_ms: u16,
+    pub max_resets: u8,
+}
+
+// Guideline "move reset" lock delay: moves and rotations restart the timer a limited number
+// of times, reaching a new lowest row gives all the resets back
+#[derive(Debug, Clone)]
+pub struct LockDelay {
+    settings: LockDelaySettings,
+    resets: u8,
+    lowest_row: Option<i8>,
+    grounded_since: Option<Instant>,
+}
+
+fn bottom_row(tetrimino: &Tetrimino) -> i8 {
+    let tetri_shape = tetrimino.to_blocks();
+    let mut bottom = tetrimino.position().1;
+
+    for column in tetri_shape.iter() {
+        for (y, block) in column.iter().enumerate() {
+            if *block {
+                bottom = bottom.min(tetrimino.position().1 - y as i8);
+            }
+        }
+    }
+    bottom
+}
+
+impl LockDelay {
+    pub fn new(settings: LockDelaySettings) -> Self {
+        Self {
+            settings,
+            resets: 0,
+            lowest_row: None,
+            grounded_since: None,
+        }
+    }
+
+    pub fn settings(&self) -> LockDelaySettings {
+        self.settings
+    }
+
+    // A new tetrimino was spawned
+    pub fn reset(&mut self) {
+        self.resets = 0;
+        self.lowest_row = None;
+        self.grounded_since = None;
+    }
+
+    // The tetrimino went down, by gravity or soft drop
+    pub fn on_step_down(&mut self, tetrimino: &Tetrimino) {
+        let row = bottom_row(tetrimino);
+        let new_lowest_row = match self.lowest_row {
+            Some(lowest_row) => row < lowest_row,
+            None => true,
+        };
+
+        if new_lowest_row {
+            self.lowest_row = Some(row);
+            self.resets = 0;
+            self.grounded_since = None;
+        }
+    }
+
+    // The tetrimino was successfully moved or rotated
+    pub fn on_move(&mut self, now: Instant) {
+        if self.grounded_since.is_some() && self.resets < self.settings.max_resets {
+            self.resets += 1;
+            self.grounded_since = Some(now);
+        }
+    }
+
+    // Called after every change of the game, the timer starts when the tetrimino touches the
+    // ground and stops when it leaves it
+    pub fn update_ground(&mut self, game: &PlayerGame, now: Instant) {
+        let grounded = game
+            .current_tetrimino()
+            .is_some_and(|tetrimino| !tetrimino.can_move_to(game.matrix(), Direction::Down));
+
+        if !grounded {
+            self.grounded_since = None;
+        } else if self.grounded_since.is_none() {
+            self.grounded_since = Some(now);
+        }
+    }
+
+    // The tetrimino is on the ground, tells if it's time to lock it
+    pub fn should_lock(&self, now: Instant) -> bool {
+        match self.grounded_since {
+            Some(grounded_since) => {
+                self.resets >= self.settings.max_resets
+                    || now.duration_since(grounded_since)
+                        >= Duration::from_millis(self.settings.delay_ms.into())
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameSettings;
+    use crate::game_mode::{GameMode, GameOptions};
+    use crate::gravity::GravityCurve;
+    use crate::randomizer::RandomizerKind;
+
+    const SETTINGS: LockDelaySettings = LockDelaySettings {
+        delay_ms: DEFAULT_LOCK_DELAY_MS,
+        max_resets: DEFAULT_MAX_LOCK_RESETS,
+    };
+
+    fn game() -> PlayerGame {
+        let settings = GameSettings {
+            mode: GameMode::Versus,
+            gravity_curve: GravityCurve::Classic,
+            randomizer: RandomizerKind::SevenBag,
+            lines_per_level: 10,
+            lock_delay: SETTINGS,
+        };
+        let mut game = PlayerGame::new(0, String::new(), 0, &settings, &GameOptions::default());
+        game.new_tetrimino();
+        game
+    }
+
+    fn move_tetrimino(game: &mut PlayerGame, direction: Direction) {
+        if let Some(tetrimino) = game.current_tetrimino_mut() {
+            tetrimino.apply_direction(direction);
+        }
+    }
+
+    fn drop_tetrimino(game: &mut PlayerGame) {
+        while game
+            .current_tetrimino()
+            .is_some_and(|tetrimino| tetrimino.can_move_to(game.matrix(), Direction::Down))
+        {
+            move_tetrimino(game, Direction::Down);
+        }
+    }
+
+    fn after(now: Instant, ms: u64) -> Instant {
+        now + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn the_timer_starts_at_touchdown() {
+        let mut game = game();
+        let mut lock_delay = LockDelay::new(SETTINGS);
+        let now = Instant::now();
+
+        lock_delay.update_ground(&game, now);
+        assert!(!lock_delay.should_lock(after(now, 10_000)));
+
+        drop_tetrimino(&mut game);
+        let touchdown = after(now, 1_000);
+        lock_delay.update_ground(&game, touchdown);
+        // Already on the ground, the timer keeps its start
+        lock_delay.update_ground(&game, after(touchdown, 300));
+        assert!(!lock_delay.should_lock(after(touchdown, 499)));
+        assert!(lock_delay.should_lock(after(touchdown, 500)));
+    }
+
+    #[test]
+    fn leaving_the_ground_stops_the_timer() {
+        let mut game = game();
+        let mut lock_delay = LockDelay::new(SETTINGS);
+        let now = Instant::now();
+
+        drop_tetrimino(&mut game);
+        lock_delay.update_ground(&game, now);
+        move_tetrimino(&mut game, Direction::Up);
+        lock_delay.update_ground(&game, after(now, 100));
+        assert!(!lock_delay.should_lock(after(now, 1_000)));
+    }
+
+    #[test]
+    fn moves_restart_the_timer_until_the_cap() {
+        let mut game = game();
+        let mut lock_delay = LockDelay::new(SETTINGS);
+        let now = Instant::now();
+
+        // Moves in the air are free
+        for _ in 0..DEFAULT_MAX_LOCK_RESETS {
+            lock_delay.on_move(now);
+        }
+        drop_tetrimino(&mut game);
+        lock_delay.update_ground(&game, now);
+        assert!(!lock_delay.should_lock(now));
+
+        lock_delay.on_move(after(now, 400));
+        assert!(!lock_delay.should_lock(after(now, 600)));
+        assert!(lock_delay.should_lock(after(now, 900)));
+
+        for _ in 1..DEFAULT_MAX_LOCK_RESETS {
+            lock_delay.on_move(after(now, 500));
+        }
+        assert!(lock_delay.should_lock(after(now, 500)));
+    }
+
+    #[test]
+    fn a_new_lowest_row_gives_the_resets_back() {
+        let mut game = game();
+        let mut lock_delay = LockDelay::new(SETTINGS);
+        let now = Instant::now();
+
+        lock_delay.on_step_down(&game.current_tetrimino().unwrap());
+        move_tetrimino(&mut game, Direction::Down);
+        lock_delay.on_step_down(&game.current_tetrimino().unwrap());
+        lock_delay.update_ground(&game, now);
+        lock_delay.on_move(now);
+
+        drop_tetrimino(&mut game);
+        lock_delay.update_ground(&game, now);
+        for _ in 0..DEFAULT_MAX_LOCK_RESETS {
+            lock_delay.on_move(now);
+        }
+        assert!(lock_delay.should_lock(now));
+
+        // The tetrimino is lower than ever, the resets are given back and the timer restarts
+        lock_delay.on_step_down(&game.current_tetrimino().unwrap());
+        assert!(!lock_delay.should_lock(after(now, 1_000)));
+        lock_delay.update_ground(&game, now);
+        assert!(!lock_delay.should_lock(now));
+
+        // The same row again does not
+        lock_delay.on_step_down(&game.current_tetrimino().unwrap());
+        for _ in 0..DEFAULT_MAX_LOCK_RESETS {
+            lock_delay.on_move(now);
+        }
+        assert!(lock_delay.should_lock(now));
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::PlayerMinimalInfos;
+use crate::game_mode::GameMode;
+
+pub type PoolId = u32;
+
+// A running pool, as listed for the spectators
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PoolInfos {
+    pub id: PoolId,
+    pub mode: GameMode,
+    pub players: Vec<PlayerMinimalInfos>,
+}
+
+// Final ranking of a match, from the winner to the first player eliminated
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MatchResult {
+    pub placements: Vec<PlayerMinimalInfos>,
+}
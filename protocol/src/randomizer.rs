@@ -0,0 +1,318 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{rng::GameRng, tetrimino::TetriminoType};
+
+const ALL_TETRIMINOS: [TetriminoType; 7] = [
+    TetriminoType::I,
+    TetriminoType::J,
+    TetriminoType::L,
+    TetriminoType::O,
+    TetriminoType::S,
+    TetriminoType::T,
+    TetriminoType::Z,
+];
+
+pub trait Randomizer {
+    fn next_tetrimino(&mut self, rng: &mut GameRng) -> TetriminoType;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RandomizerKind {
+    SevenBag,
+    FourteenBag,
+    Random,
+    Tgm,
+    Nes,
+}
+
+impl RandomizerKind {
+    pub fn build(self) -> AnyRandomizer {
+        match self {
+            Self::SevenBag => AnyRandomizer::Bag(Bag::new(1)),
+            Self::FourteenBag => AnyRandomizer::Bag(Bag::new(2)),
+            Self::Random => AnyRandomizer::Random(PureRandom),
+            Self::Tgm => AnyRandomizer::Tgm(TgmHistory::new()),
+            Self::Nes => AnyRandomizer::Nes(NesRandom::new()),
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "7bag" => Ok(Self::SevenBag),
+            "14bag" => Ok(Self::FourteenBag),
+            "random" => Ok(Self::Random),
+            "tgm" => Ok(Self::Tgm),
+            "nes" => Ok(Self::Nes),
+            _ => Err(()),
+        }
+    }
+}
+
+// Every tetrimino `copies` times, drawn without replacement
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bag {
+    copies: usize,
+    list: Vec<TetriminoType>,
+}
+
+impl Bag {
+    pub fn new(copies: usize) -> Self {
+        Self {
+            copies,
+            list: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next_tetrimino(&mut self, rng: &mut GameRng) -> TetriminoType {
+        if self.list.is_empty() {
+            for _ in 0..self.copies {
+                self.list.extend_from_slice(&ALL_TETRIMINOS);
+            }
+        }
+        let random = rng.gen_range(0, self.list.len());
+        self.list.swap_remove(random)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PureRandom;
+
+impl Randomizer for PureRandom {
+    fn next_tetrimino(&mut self, rng: &mut GameRng) -> TetriminoType {
+        ALL_TETRIMINOS[rng.gen_range(0, ALL_TETRIMINOS.len())]
+    }
+}
+
+// TGM2 randomizer: rerolls up to 6 times when the piece is in the last 4 dealt
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TgmHistory {
+    history: Vec<TetriminoType>,
+    first: bool,
+}
+
+impl TgmHistory {
+    const ROLLS: usize = 6;
+
+    pub fn new() -> Self {
+        Self {
+            history: vec![
+                TetriminoType::Z,
+                TetriminoType::S,
+                TetriminoType::S,
+                TetriminoType::Z,
+            ],
+            first: true,
+        }
+    }
+}
+
+impl Default for TgmHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Randomizer for TgmHistory {
+    fn next_tetrimino(&mut self, rng: &mut GameRng) -> TetriminoType {
+        let tetrimino = if self.first {
+            // The first piece is never an S, Z or O
+            self.first = false;
+            [
+                TetriminoType::I,
+                TetriminoType::J,
+                TetriminoType::L,
+                TetriminoType::T,
+            ][rng.gen_range(0, 4)]
+        } else {
+            let mut tetrimino = ALL_TETRIMINOS[rng.gen_range(0, ALL_TETRIMINOS.len())];
+            for _ in 1..Self::ROLLS {
+                if !self.history.contains(&tetrimino) {
+                    break;
+                }
+                tetrimino = ALL_TETRIMINOS[rng.gen_range(0, ALL_TETRIMINOS.len())];
+            }
+            tetrimino
+        };
+
+        self.history.remove(0);
+        self.history.push(tetrimino);
+        tetrimino
+    }
+}
+
+// NES randomizer: a single reroll when the roll repeats the last piece or hits the 8th slot
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NesRandom {
+    last: TetriminoType,
+}
+
+impl NesRandom {
+    pub fn new() -> Self {
+        Self {
+            last: TetriminoType::None,
+        }
+    }
+}
+
+impl Default for NesRandom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Randomizer for NesRandom {
+    fn next_tetrimino(&mut self, rng: &mut GameRng) -> TetriminoType {
+        let roll = rng.gen_range(0, ALL_TETRIMINOS.len() + 1);
+        let tetrimino = if roll == ALL_TETRIMINOS.len() || ALL_TETRIMINOS[roll] == self.last {
+            ALL_TETRIMINOS[rng.gen_range(0, ALL_TETRIMINOS.len())]
+        } else {
+            ALL_TETRIMINOS[roll]
+        };
+
+        self.last = tetrimino;
+        tetrimino
+    }
+}
+
+// Serializable wrapper so the randomizer state can travel with the PlayerGame
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum AnyRandomizer {
+    Bag(Bag),
+    Random(PureRandom),
+    Tgm(TgmHistory),
+    Nes(NesRandom),
+}
+
+impl AnyRandomizer {
+    pub fn kind(&self) -> RandomizerKind {
+        match self {
+            Self::Bag(Bag { copies: 1, .. }) => RandomizerKind::SevenBag,
+            Self::Bag(_) => RandomizerKind::FourteenBag,
+            Self::Random(_) => RandomizerKind::Random,
+            Self::Tgm(_) => RandomizerKind::Tgm,
+            Self::Nes(_) => RandomizerKind::Nes,
+        }
+    }
+}
+
+impl Randomizer for AnyRandomizer {
+    fn next_tetrimino(&mut self, rng: &mut GameRng) -> TetriminoType {
+        match self {
+            Self::Bag(randomizer) => randomizer.next_tetrimino(rng),
+            Self::Random(randomizer) => randomizer.next_tetrimino(rng),
+            Self::Tgm(randomizer) => randomizer.next_tetrimino(rng),
+            Self::Nes(randomizer) => randomizer.next_tetrimino(rng),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::rng_from_seed;
+
+    fn deal(kind: RandomizerKind, seed: u64, count: usize) -> Vec<TetriminoType> {
+        let mut randomizer = kind.build();
+        let mut rng = rng_from_seed(seed);
+        (0..count)
+            .map(|_| randomizer.next_tetrimino(&mut rng))
+            .collect()
+    }
+
+    fn assert_bags(dealt: &[TetriminoType], copies: usize) {
+        for bag in dealt.chunks(ALL_TETRIMINOS.len() * copies) {
+            for tetrimino in ALL_TETRIMINOS.iter() {
+                let count = bag.iter().filter(|dealt| *dealt == tetrimino).count();
+                assert_eq!(count, copies, "{:?} in the bag {:?}", tetrimino, bag);
+            }
+        }
+    }
+
+    #[test]
+    fn seven_bag_deals_every_tetrimino_once_per_bag() {
+        for seed in 0..20 {
+            assert_bags(&deal(RandomizerKind::SevenBag, seed, 7 * 10), 1);
+        }
+    }
+
+    #[test]
+    fn fourteen_bag_deals_every_tetrimino_twice_per_bag() {
+        for seed in 0..20 {
+            assert_bags(&deal(RandomizerKind::FourteenBag, seed, 14 * 10), 2);
+        }
+    }
+
+    // Share of the dealt tetriminos found in the `window` dealt before them
+    fn repeat_rate(dealt: &[TetriminoType], window: usize) -> f64 {
+        let repeats = (window..dealt.len())
+            .filter(|i| dealt[i - window..*i].contains(&dealt[*i]))
+            .count();
+        repeats as f64 / (dealt.len() - window) as f64
+    }
+
+    #[test]
+    fn tgm_never_starts_with_s_z_or_o() {
+        for seed in 0..100 {
+            let first = deal(RandomizerKind::Tgm, seed, 1)[0];
+            assert!(
+                ![TetriminoType::S, TetriminoType::Z, TetriminoType::O].contains(&first),
+                "{:?} first with the seed {}",
+                first,
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn tgm_rerolls_the_last_four_tetriminos() {
+        let dealt = deal(RandomizerKind::Tgm, 42, 10_000);
+        // Six rolls all in the history, at most (4/7)^6
+        assert!(repeat_rate(&dealt, 4) < 0.04);
+        assert!(repeat_rate(&deal(RandomizerKind::Random, 42, 10_000), 4) > 0.4);
+        for tetrimino in ALL_TETRIMINOS.iter() {
+            assert!(dealt.contains(tetrimino));
+        }
+    }
+
+    #[test]
+    fn nes_rerolls_the_last_tetrimino_once() {
+        let dealt = deal(RandomizerKind::Nes, 42, 10_000);
+        // The roll repeats or hits the 8th slot, then the reroll repeats: 2/8 * 1/7
+        let rate = repeat_rate(&dealt, 1);
+        assert!(rate > 0.025 && rate < 0.05, "{} of repeats", rate);
+        assert!(repeat_rate(&deal(RandomizerKind::Random, 42, 10_000), 1) > 0.12);
+    }
+
+    #[test]
+    fn randomizers_keep_their_kind() {
+        for kind in [
+            RandomizerKind::SevenBag,
+            RandomizerKind::FourteenBag,
+            RandomizerKind::Random,
+            RandomizerKind::Tgm,
+            RandomizerKind::Nes,
+        ] {
+            assert_eq!(kind.build().kind(), kind);
+        }
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_tetriminos() {
+        assert_eq!(
+            deal(RandomizerKind::SevenBag, 42, 100),
+            deal(RandomizerKind::SevenBag, 42, 100)
+        );
+        assert_ne!(
+            deal(RandomizerKind::SevenBag, 42, 100),
+            deal(RandomizerKind::SevenBag, 43, 100)
+        );
+    }
+}
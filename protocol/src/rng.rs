@@ -0,0 +1,55 @@
+use rand::{Error, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+pub type Seed = u64;
+
+// SplitMix64: small, fast and serializable, so that a game can be replayed from its seed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for GameRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            state: u64::from_le_bytes(seed),
+        }
+    }
+}
+
+pub fn rng_from_seed(seed: Seed) -> GameRng {
+    GameRng::from_seed(seed.to_le_bytes())
+}
+
+// A seed of its own for each use of a seed, so that two rngs never follow the same sequence
+pub fn derive_seed(seed: Seed, stream: u64) -> Seed {
+    rng_from_seed(seed ^ stream).next_u64()
+}
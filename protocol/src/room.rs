@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game_mode::{GameMode, GameOptions};
+
+// Six digits code given to the friends joining a room
+pub type RoomCode = u32;
+
+// Chosen by the host when creating a room
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomSettings {
+    pub name: String,
+    pub size: u16,
+    pub mode: GameMode,
+    pub options: GameOptions,
+    // Private rooms are left out of the lobby list, they can only be joined with their code
+    pub private: bool,
+}
+
+// What the lobby shows of a room
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomInfos {
+    pub code: RoomCode,
+    pub name: String,
+    pub host: String,
+    pub players: Vec<String>,
+    pub size: u16,
+    pub mode: GameMode,
+    pub private: bool,
+}
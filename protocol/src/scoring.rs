@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+
+use crate::spin::Spin;
+
+pub const DEFAULT_LINES_PER_LEVEL: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineClear {
+    pub rows: Vec<u8>,
+    pub spin: Spin,
+    pub perfect_clear: bool,
+    // Combo counter after this placement, 0 for the first clear of a chain
+    pub combo: Option<u32>,
+    // This clear continues a back-to-back chain
+    pub back_to_back: bool,
+}
+
+impl LineClear {
+    pub fn lines(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    // Tetrises and spins with lines keep the back-to-back chain going
+    pub fn is_difficult(&self) -> bool {
+        self.lines() == 4 || (self.lines() > 0 && self.spin != Spin::None)
+    }
+}
+
+// Guideline scoring: https://tetris.wiki/Scoring
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Score {
+    score: u32,
+    start_level: u32,
+    lines: u32,
+    lines_per_level: u32,
+    combo: Option<u32>,
+    back_to_back: bool,
+}
+
+impl Score {
+    pub fn new(start_level: u32, lines_per_level: u32) -> Self {
+        Self {
+            score: 0,
+            start_level,
+            lines: 0,
+            lines_per_level,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn level(&self) -> u32 {
+        self.start_level + self.lines / self.lines_per_level
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    pub fn add_soft_drop(&mut self, rows: u32) {
+        self.score += rows;
+    }
+
+    pub fn add_hard_drop(&mut self, rows: u32) {
+        self.score += 2 * rows;
+    }
+
+    fn clear_points(clear: &LineClear) -> u32 {
+        match (clear.spin, clear.lines()) {
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, 4) => 800,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+            (_, _) => 0,
+        }
+    }
+
+    fn perfect_clear_points(clear: &LineClear) -> u32 {
+        match (clear.perfect_clear, clear.lines()) {
+            (false, _) => 0,
+            (true, 1) => 800,
+            (true, 2) => 1200,
+            (true, 3) => 1800,
+            (true, _) => 2000,
+        }
+    }
+
+    pub fn add_clear(&mut self, clear: &LineClear) -> u32 {
+        let level = self.level();
+        let mut points = Self::clear_points(clear);
+
+        if clear.lines() > 0 {
+            if clear.back_to_back {
+                points = points * 3 / 2;
+            }
+            points += Self::perfect_clear_points(clear);
+            self.back_to_back = clear.is_difficult();
+
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            points += 50 * combo;
+            self.combo = Some(combo);
+        } else {
+            self.combo = None;
+        }
+
+        points *= level;
+        self.score += points;
+        self.lines += clear.lines();
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: u8, spin: Spin) -> LineClear {
+        LineClear {
+            rows: (0..lines).collect(),
+            spin,
+            perfect_clear: false,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    // Points of a clear alone, out of any combo
+    fn points(level: u32, clear: &LineClear) -> u32 {
+        Score::new(level, DEFAULT_LINES_PER_LEVEL).add_clear(clear)
+    }
+
+    #[test]
+    fn line_clears_score_by_level() {
+        assert_eq!(points(1, &clear(1, Spin::None)), 100);
+        assert_eq!(points(1, &clear(2, Spin::None)), 300);
+        assert_eq!(points(1, &clear(3, Spin::None)), 500);
+        assert_eq!(points(1, &clear(4, Spin::None)), 800);
+        assert_eq!(points(3, &clear(4, Spin::None)), 2400);
+    }
+
+    #[test]
+    fn spins_score_more() {
+        assert_eq!(points(1, &clear(0, Spin::Mini)), 100);
+        assert_eq!(points(1, &clear(1, Spin::Mini)), 200);
+        assert_eq!(points(1, &clear(2, Spin::Mini)), 400);
+        assert_eq!(points(1, &clear(0, Spin::Full)), 400);
+        assert_eq!(points(1, &clear(1, Spin::Full)), 800);
+        assert_eq!(points(1, &clear(2, Spin::Full)), 1200);
+        assert_eq!(points(1, &clear(3, Spin::Full)), 1600);
+    }
+
+    #[test]
+    fn back_to_back_and_perfect_clear_bonuses() {
+        let mut tetris = clear(4, Spin::None);
+        tetris.back_to_back = true;
+        assert_eq!(points(1, &tetris), 1200);
+
+        let mut perfect_clear = clear(4, Spin::None);
+        perfect_clear.perfect_clear = true;
+        assert_eq!(points(1, &perfect_clear), 800 + 2000);
+    }
+
+    #[test]
+    fn combos_add_50_per_step_until_a_placement_clears_nothing() {
+        let mut score = Score::new(2, DEFAULT_LINES_PER_LEVEL);
+
+        assert_eq!(score.add_clear(&clear(1, Spin::None)), 200);
+        assert_eq!(score.add_clear(&clear(1, Spin::None)), 300);
+        assert_eq!(score.add_clear(&clear(1, Spin::None)), 400);
+        assert_eq!(score.combo(), Some(2));
+        assert_eq!(score.add_clear(&clear(0, Spin::None)), 0);
+        assert_eq!(score.combo(), None);
+        assert_eq!(score.add_clear(&clear(1, Spin::None)), 200);
+        assert_eq!(score.score(), 1100);
+    }
+
+    #[test]
+    fn level_goes_up_with_the_lines() {
+        let mut score = Score::new(1, 10);
+        for _ in 0..2 {
+            score.add_clear(&clear(4, Spin::None));
+        }
+        assert_eq!(score.level(), 1);
+        score.add_clear(&clear(2, Spin::None));
+        assert_eq!(score.lines(), 10);
+        assert_eq!(score.level(), 2);
+        assert!(!score.back_to_back());
+    }
+}
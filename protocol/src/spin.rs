@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::Matrix,
+    tetrimino::{Tetrimino, TetriminoType},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+// Last successful maneuver of the current tetrimino, with the index of the wall kick test
+// that made the rotation possible
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LastAction {
+    Move,
+    Rotation(usize),
+}
+
+const DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+// Three-corner T-spin rule: https://tetris.wiki/T-Spin
+pub fn detect_spin(tetrimino: &Tetrimino, matrix: &Matrix, last_action: LastAction) -> Spin {
+    let kick = if let LastAction::Rotation(kick) = last_action {
+        kick
+    } else {
+        return Spin::None;
+    };
+    if tetrimino.ttype() != TetriminoType::T {
+        return Spin::None;
+    }
+
+    let (x, y) = (tetrimino.position().0 + 1, tetrimino.position().1 - 1);
+    let occupied = |x: i8, y: i8| {
+        x < 0
+            || y < 0
+            || x >= matrix[0].len() as i8
+            || y >= matrix.len() as i8
+            || matrix[y as usize][x as usize].is_some()
+    };
+    let (dx, dy) = *DIRECTIONS
+        .iter()
+        .find(|(dx, dy)| {
+            tetrimino.check_position(x + dx, y + dy) && !tetrimino.check_position(x - dx, y - dy)
+        })
+        .unwrap();
+
+    let front_corners = [(x + dx + dy, y + dy + dx), (x + dx - dy, y + dy - dx)]
+        .iter()
+        .filter(|(x, y)| occupied(*x, *y))
+        .count();
+    let back_corners = [(x - dx + dy, y - dy + dx), (x - dx - dy, y - dy - dx)]
+        .iter()
+        .filter(|(x, y)| occupied(*x, *y))
+        .count();
+
+    if front_corners + back_corners < 3 {
+        Spin::None
+    } else if front_corners == 2 || kick == 4 {
+        // The last SRS kick upgrades a mini to a full T-spin
+        Spin::Full
+    } else {
+        Spin::Mini
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Direction;
+
+    const EMPTY: Matrix = [[None; 10]; 32];
+
+    fn drop(tetrimino: &mut Tetrimino, matrix: &Matrix) {
+        while tetrimino.can_move_to(matrix, Direction::Down) {
+            tetrimino.apply_direction(Direction::Down);
+        }
+    }
+
+    fn center(tetrimino: &Tetrimino) -> (usize, usize) {
+        let (x, y) = tetrimino.position();
+        ((x + 1) as usize, (y - 1) as usize)
+    }
+
+    // A T pointing up on the floor, the floor fills both of its back corners
+    fn t_on_the_floor() -> Tetrimino {
+        let mut tetrimino = Tetrimino::new(TetriminoType::T);
+        drop(&mut tetrimino, &EMPTY);
+        tetrimino
+    }
+
+    // A T pointing down in the hole of a full bottom row
+    fn t_in_a_slot() -> (Tetrimino, Matrix) {
+        let mut tetrimino = Tetrimino::new(TetriminoType::T);
+        tetrimino.rotate(&EMPTY, false);
+        tetrimino.rotate(&EMPTY, false);
+        let mut matrix = EMPTY;
+        let (x, _) = center(&tetrimino);
+        for (column, cell) in matrix[0].iter_mut().enumerate() {
+            if column != x {
+                *cell = Some(TetriminoType::O);
+            }
+        }
+        drop(&mut tetrimino, &matrix);
+        (tetrimino, matrix)
+    }
+
+    #[test]
+    fn two_corners_are_not_a_spin() {
+        let tetrimino = t_on_the_floor();
+        assert_eq!(
+            detect_spin(&tetrimino, &EMPTY, LastAction::Rotation(0)),
+            Spin::None
+        );
+
+        let (tetrimino, matrix) = t_in_a_slot();
+        assert_eq!(
+            detect_spin(&tetrimino, &matrix, LastAction::Rotation(0)),
+            Spin::None
+        );
+    }
+
+    #[test]
+    fn three_corners_with_both_front_ones_are_a_full_spin() {
+        let (tetrimino, mut matrix) = t_in_a_slot();
+        let (x, y) = center(&tetrimino);
+        assert!(tetrimino.check_position(x as i8, y as i8 - 1));
+        matrix[y + 1][x - 1] = Some(TetriminoType::O);
+
+        assert_eq!(
+            detect_spin(&tetrimino, &matrix, LastAction::Rotation(0)),
+            Spin::Full
+        );
+    }
+
+    #[test]
+    fn three_corners_with_one_front_one_are_a_mini_spin() {
+        let tetrimino = t_on_the_floor();
+        let (x, y) = center(&tetrimino);
+        let mut matrix = EMPTY;
+        matrix[y + 1][x - 1] = Some(TetriminoType::O);
+
+        assert_eq!(
+            detect_spin(&tetrimino, &matrix, LastAction::Rotation(0)),
+            Spin::Mini
+        );
+        // The last kick test turns it into a full one
+        assert_eq!(
+            detect_spin(&tetrimino, &matrix, LastAction::Rotation(4)),
+            Spin::Full
+        );
+    }
+
+    #[test]
+    fn only_rotated_t_pieces_spin() {
+        let (tetrimino, mut matrix) = t_in_a_slot();
+        let (x, y) = center(&tetrimino);
+        matrix[y + 1][x - 1] = Some(TetriminoType::O);
+        assert_eq!(
+            detect_spin(&tetrimino, &matrix, LastAction::Move),
+            Spin::None
+        );
+
+        let mut tetrimino = Tetrimino::new(TetriminoType::L);
+        drop(&mut tetrimino, &EMPTY);
+        let mut matrix = EMPTY;
+        for row in matrix[1..3].iter_mut() {
+            row[0] = Some(TetriminoType::O);
+            row[9] = Some(TetriminoType::O);
+        }
+        assert_eq!(
+            detect_spin(&tetrimino, &matrix, LastAction::Rotation(0)),
+            Spin::None
+        );
+    }
+}
@@ -91,6 +91,11 @@ impl Tetrimino {
     }
 
     pub fn rotate(&mut self, matrix: &Matrix, revert_direction: bool) -> bool {
+        self.rotate_with_kick(matrix, revert_direction).is_some()
+    }
+
+    // Returns the index of the wall kick test used by a successful rotation
+    pub fn rotate_with_kick(&mut self, matrix: &Matrix, revert_direction: bool) -> Option<usize> {
         let mut rotated_tetri = self.clone();
         if revert_direction {
             rotated_tetri.rotation = (self.rotation + 1) % 4;
@@ -98,8 +103,8 @@ impl Tetrimino {
             rotated_tetri.rotation = (self.rotation + 3) % 4;
         }
 
-        let mut success = false;
-        for (x, y) in wall_kicks_tests_list(
+        let mut success = None;
+        for (kick, (x, y)) in wall_kicks_tests_list(
             self.ttype,
             if revert_direction {
                 (rotated_tetri.rotation + 3) % 4
@@ -108,6 +113,7 @@ impl Tetrimino {
             },
         )
         .iter()
+        .enumerate()
         {
             if revert_direction {
                 rotated_tetri.position.0 -= x;
@@ -117,7 +123,7 @@ impl Tetrimino {
                 rotated_tetri.position.1 -= y;
             }
             if rotated_tetri.is_valid(matrix) {
-                success = true;
+                success = Some(kick);
                 break;
             }
             if revert_direction {
@@ -129,7 +135,7 @@ impl Tetrimino {
             }
         }
 
-        if success {
+        if success.is_some() {
             *self = rotated_tetri;
         }
         success
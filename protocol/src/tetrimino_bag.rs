@@ -1,35 +1,30 @@
-use rand::{self, Rng};
 use serde::{Deserialize, Serialize};
 
-use crate::tetrimino::TetriminoType;
+use crate::{
+    randomizer::{AnyRandomizer, Randomizer, RandomizerKind},
+    rng::{rng_from_seed, GameRng, Seed},
+    tetrimino::TetriminoType,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub struct TetriminoBag(Vec<TetriminoType>);
+pub struct TetriminoBag {
+    randomizer: AnyRandomizer,
+    rng: GameRng,
+}
 
 impl TetriminoBag {
-    pub fn new() -> Self {
-        Self(Self::reseted_list())
+    pub fn new(seed: Seed, kind: RandomizerKind) -> Self {
+        Self {
+            randomizer: kind.build(),
+            rng: rng_from_seed(seed),
+        }
     }
 
-    fn reseted_list() -> Vec<TetriminoType> {
-        vec![
-            TetriminoType::I,
-            TetriminoType::J,
-            TetriminoType::L,
-            TetriminoType::O,
-            TetriminoType::S,
-            TetriminoType::T,
-            TetriminoType::Z,
-        ]
+    pub fn kind(&self) -> RandomizerKind {
+        self.randomizer.kind()
     }
 
     pub fn choose_a_tetrimino(&mut self) -> TetriminoType {
-        let random = rand::thread_rng().gen_range(0, self.0.len());
-        let tetrimino = self.0.swap_remove(random);
-
-        if self.0.len() == 0 {
-            self.0 = Self::reseted_list();
-        }
-        return tetrimino;
+        self.randomizer.next_tetrimino(&mut self.rng)
     }
 }
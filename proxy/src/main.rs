@@ -1,7 +1,11 @@
-use fetris_protocol::{game::Input, ClientRequest, ServerRequest};
+use fetris_protocol::{
+    game::Input,
+    game_mode::GameOptions,
+    handshake::{self, ClientKind},
+    ClientRequest, ServerRequest,
+};
 use serde_json;
 use std::env;
-use std::io::Write;
 use std::net::TcpListener;
 use std::net::TcpStream;
 use std::thread::spawn;
@@ -35,6 +39,10 @@ fn main() {
             let stream_write = stream_read.try_clone().unwrap();
             let mut server_write =
                 TcpStream::connect(format!("{}:{}", SERVER_ADDR, server_port)).unwrap();
+            if let Err(err) = handshake::hello(&server_write, ClientKind::WebProxy) {
+                println!("{}", err);
+                return;
+            }
             let server_read = server_write.try_clone().unwrap();
             let mut ws_read = accept(stream_read).unwrap();
             let mut ws_write = WebSocket::from_raw_socket(stream_write, Role::Server, None);
@@ -51,8 +59,8 @@ fn main() {
                     break;
                 }
             });
-            server_write
-                .write(&ClientRequest::AskForAGame.into_bytes())
+            ClientRequest::AskForAGame(None, GameOptions::default())
+                .write_to(&mut server_write)
                 .unwrap();
             loop {
                 let msg = if let Ok(msg) = ws_read.read_message() {
@@ -77,8 +85,8 @@ fn main() {
                 };
                 if let Some(input) = input {
                     println!("Input sent: {:?}", input);
-                    server_write
-                        .write(&ClientRequest::Input(input).into_bytes())
+                    ClientRequest::Input(input)
+                        .write_to(&mut server_write)
                         .unwrap();
                 }
             }
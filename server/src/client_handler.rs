@@ -1,28 +1,91 @@
-use fetris_protocol::ClientRequest;
+use fetris_protocol::{
+    handshake::PROTOCOL_VERSION, BadRequestReason, ClientRequest, ProtocolError, ServerRequest,
+};
 use std::net::TcpStream;
 use std::sync::mpsc::Sender;
 
 use crate::network::{NetworkAction, NetworkPacket, StreamList};
 
+// A client sending that many undecodable requests is not worth answering anymore
+const MAX_MALFORMED_REQUESTS: u32 = 3;
+
+// The first request must be a Hello with our protocol version, other clients are sent away
+fn handshake(stream: &TcpStream) -> Result<(), ()> {
+    let addr = stream.peer_addr().map_err(|_| ())?;
+    let answer = match ClientRequest::from_reader(stream) {
+        Ok(ClientRequest::Hello {
+            protocol_version,
+            client_kind,
+        }) if protocol_version == PROTOCOL_VERSION => {
+            println!("{} connected with a {:?} client", addr, client_kind);
+            ServerRequest::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            }
+        }
+        _ => {
+            println!("{} speaks another protocol version", addr);
+            let _ = ServerRequest::VersionMismatch {
+                server_version: PROTOCOL_VERSION,
+            }
+            .write_to(stream);
+            return Err(());
+        }
+    };
+    answer.write_to(stream).map_err(|_| ())
+}
+
 pub fn client_handler(stream: TcpStream, stream_list: StreamList, sender: Sender<NetworkPacket>) {
-    let addr = stream_list.open_stream(&stream).unwrap();
-    sender
+    if handshake(&stream).is_err() {
+        return;
+    }
+
+    let addr = match stream_list.open_stream(&stream) {
+        Ok(addr) => addr,
+        Err(err) => {
+            println!("Cannot open stream: {}", err);
+            return;
+        }
+    };
+    // The game thread only stops with the server, a failed send means it is shutting down
+    if sender
         .send(NetworkPacket::new(addr, NetworkAction::OpenStream))
-        .unwrap();
+        .is_err()
+    {
+        stream_list.close_stream(&addr);
+        return;
+    }
 
+    let mut malformed_requests = 0;
     loop {
-        if let Ok(request) = ClientRequest::from_reader(&stream) {
-            sender
-                .send(NetworkPacket::new(addr, NetworkAction::Request(request)))
-                .unwrap();
-        } else {
-            break;
+        match ClientRequest::from_reader(&stream) {
+            Ok(request) => {
+                if sender
+                    .send(NetworkPacket::new(addr, NetworkAction::Request(request)))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            // The frame was read whole, the stream is still in sync with the client
+            Err(ProtocolError::Malformed(err)) => {
+                println!("{} sent a malformed request: {}", addr, err);
+                let request = ServerRequest::BadRequest(BadRequestReason::Malformed);
+                if let Err(err) = stream_list.send_to(&addr, request) {
+                    println!("Cannot answer {}: {}", addr, err);
+                }
+                malformed_requests += 1;
+                if malformed_requests >= MAX_MALFORMED_REQUESTS {
+                    println!("{} closing: too many malformed requests", addr);
+                    break;
+                }
+            }
+            Err(err) => {
+                println!("{} closing: {}", addr, err);
+                break;
+            }
         }
     }
 
-    sender
-        .send(NetworkPacket::new(addr, NetworkAction::CloseStream))
-        .unwrap();
-
+    let _ = sender.send(NetworkPacket::new(addr, NetworkAction::CloseStream));
     stream_list.close_stream(&addr);
 }
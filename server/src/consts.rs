@@ -1,3 +1,9 @@
 pub const DEFAULT_PORT: u16 = 3001;
 pub const DEFAULT_POOL_SIZE: u16 = 2;
-pub const CALL_EVERY_MS: u16 = 200;
+pub const DEFAULT_MODE: &str = "versus";
+pub const DEFAULT_RANDOMIZER: &str = "7bag";
+pub const DEFAULT_GRAVITY: &str = "classic";
+pub const DEFAULT_ATTACK_TABLE: &str = "t99";
+pub const DEFAULT_GARBAGE: &str = "clean";
+pub const DEFAULT_TARGETING: &str = "even";
+pub const DEFAULT_IDLE_TIMEOUT: u64 = 30;
@@ -0,0 +1,168 @@
+use std::str::FromStr;
+
+use fetris_protocol::scoring::LineClear;
+use fetris_protocol::spin::Spin;
+
+// Tetris 99 and Puyo Puyo Tetris both follow the guideline for combos and perfect clears
+const GUIDELINE_COMBO_TABLE: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const GUIDELINE_PERFECT_CLEAR_GARBAGE: u32 = 10;
+// TETR.IO multiplies the attack by the combo rather than adding a table
+const TETRIO_PERFECT_CLEAR_GARBAGE: u32 = 10;
+
+// Number of garbage lines sent for a line clear, as in the games our players come from
+#[derive(Clone, Copy, PartialEq)]
+pub enum AttackTable {
+    Tetris99,
+    PuyoPuyoTetris,
+    Tetrio,
+}
+
+fn base_attack(clear: &LineClear) -> u32 {
+    match (clear.spin, clear.lines()) {
+        (Spin::None, 2) => 1,
+        (Spin::None, 3) => 2,
+        (Spin::None, 4) => 4,
+        (Spin::Mini, 2) => 1,
+        (Spin::Full, x) => 2 * x,
+        (_, _) => 0,
+    }
+}
+
+fn combo_attack(clear: &LineClear) -> u32 {
+    let combo = clear.combo.unwrap_or(0) as usize;
+    GUIDELINE_COMBO_TABLE[combo.min(GUIDELINE_COMBO_TABLE.len() - 1)]
+}
+
+impl AttackTable {
+    pub fn attack(self, clear: &LineClear) -> u32 {
+        if clear.lines() == 0 {
+            return 0;
+        }
+
+        let mut attack = base_attack(clear);
+        match self {
+            Self::Tetris99 | Self::PuyoPuyoTetris => {
+                // Puyo Puyo Tetris rewards a back-to-back T-spin triple with one more line
+                let tst = clear.spin == Spin::Full && clear.lines() == 3;
+                if clear.back_to_back {
+                    attack += if self == Self::PuyoPuyoTetris && tst {
+                        2
+                    } else {
+                        1
+                    };
+                }
+                attack += combo_attack(clear);
+                if clear.perfect_clear {
+                    attack += GUIDELINE_PERFECT_CLEAR_GARBAGE;
+                }
+            }
+            // https://tetris.wiki/TETR.IO#Garbage
+            Self::Tetrio => {
+                if clear.back_to_back {
+                    attack += 1;
+                }
+                let combo = clear.combo.unwrap_or(0) as f64;
+                attack = if attack == 0 {
+                    (1. + 1.25 * combo).ln() as u32
+                } else {
+                    (attack as f64 * (1. + 0.25 * combo)) as u32
+                };
+                if clear.perfect_clear {
+                    attack += TETRIO_PERFECT_CLEAR_GARBAGE;
+                }
+            }
+        }
+        attack
+    }
+}
+
+impl FromStr for AttackTable {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "t99" => Ok(Self::Tetris99),
+            "ppt" => Ok(Self::PuyoPuyoTetris),
+            "tetrio" => Ok(Self::Tetrio),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: u8, spin: Spin, combo: u32, back_to_back: bool) -> LineClear {
+        LineClear {
+            rows: (0..lines).collect(),
+            spin,
+            perfect_clear: false,
+            combo: Some(combo),
+            back_to_back,
+        }
+    }
+
+    #[test]
+    fn guideline_base_attacks() {
+        let table = AttackTable::Tetris99;
+        assert_eq!(table.attack(&clear(1, Spin::None, 0, false)), 0);
+        assert_eq!(table.attack(&clear(2, Spin::None, 0, false)), 1);
+        assert_eq!(table.attack(&clear(3, Spin::None, 0, false)), 2);
+        assert_eq!(table.attack(&clear(4, Spin::None, 0, false)), 4);
+        assert_eq!(table.attack(&clear(1, Spin::Mini, 0, false)), 0);
+        assert_eq!(table.attack(&clear(2, Spin::Mini, 0, false)), 1);
+        assert_eq!(table.attack(&clear(2, Spin::Full, 0, false)), 4);
+        assert_eq!(table.attack(&clear(3, Spin::Full, 0, false)), 6);
+    }
+
+    #[test]
+    fn guideline_bonuses() {
+        let table = AttackTable::Tetris99;
+        assert_eq!(table.attack(&clear(4, Spin::None, 0, true)), 5);
+        assert_eq!(table.attack(&clear(1, Spin::None, 2, false)), 1);
+        assert_eq!(table.attack(&clear(1, Spin::None, 20, false)), 5);
+
+        let mut perfect = clear(4, Spin::None, 0, false);
+        perfect.perfect_clear = true;
+        assert_eq!(table.attack(&perfect), 14);
+    }
+
+    #[test]
+    fn puyo_puyo_tetris_rewards_back_to_back_t_spin_triples() {
+        let table = AttackTable::PuyoPuyoTetris;
+        assert_eq!(table.attack(&clear(3, Spin::Full, 0, true)), 8);
+        assert_eq!(table.attack(&clear(2, Spin::Full, 0, true)), 5);
+        assert_eq!(
+            AttackTable::Tetris99.attack(&clear(3, Spin::Full, 0, true)),
+            7
+        );
+    }
+
+    #[test]
+    fn tetrio_multiplies_by_the_combo() {
+        let table = AttackTable::Tetrio;
+        assert_eq!(table.attack(&clear(4, Spin::None, 0, false)), 4);
+        assert_eq!(table.attack(&clear(4, Spin::None, 2, false)), 6);
+        assert_eq!(table.attack(&clear(4, Spin::None, 2, true)), 7);
+        assert_eq!(table.attack(&clear(1, Spin::None, 0, false)), 0);
+        assert_eq!(table.attack(&clear(1, Spin::None, 4, false)), 1);
+
+        let mut perfect = clear(1, Spin::None, 0, false);
+        perfect.perfect_clear = true;
+        assert_eq!(table.attack(&perfect), 10);
+    }
+
+    #[test]
+    fn no_lines_no_attack() {
+        for table in [
+            AttackTable::Tetris99,
+            AttackTable::PuyoPuyoTetris,
+            AttackTable::Tetrio,
+        ] {
+            let mut nothing = clear(0, Spin::Full, 5, true);
+            nothing.perfect_clear = true;
+            assert_eq!(table.attack(&nothing), 0);
+        }
+    }
+}
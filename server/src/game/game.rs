@@ -4,20 +4,51 @@ use std::sync::mpsc::Receiver;
 use std::thread;
 use std::time;
 
-use fetris_protocol::{ClientRequest, ServerRequest};
+use fetris_protocol::{
+    game_mode::{GameMode, GameOptions},
+    gravity::GravityCurve,
+    lock_delay::LockDelaySettings,
+    pool::PoolId,
+    randomizer::RandomizerKind,
+    rng::Seed,
+    BadRequestReason, ClientRequest, ServerRequest,
+};
 
+use crate::game::attack::AttackTable;
+use crate::game::garbage::GarbageMode;
 use crate::game::players::Player;
-use crate::game::pools::{Pool, PoolId, PoolState};
+use crate::game::pools::{Pool, PoolState};
+use crate::game::rooms::Rooms;
+use crate::game::targeting::Targeting;
 use crate::network::{NetworkAction, NetworkPacket, StreamList};
 
 pub struct Options {
-    pub pool_size: u16
+    pub pool_size: u16,
+    // Played by the clients not choosing a mode in AskForAGame
+    pub mode: GameMode,
+    pub seed: Option<Seed>,
+    pub randomizer: RandomizerKind,
+    pub attack_table: AttackTable,
+    pub garbage_mode: GarbageMode,
+    pub gravity_curve: GravityCurve,
+    pub gravity_timer: Option<u64>,
+    pub dig_rise: Option<u64>,
+    pub idle_timeout: Option<u64>,
+    pub lines_per_level: u32,
+    pub lock_delay: LockDelaySettings,
+    pub targeting: Targeting,
 }
 
-pub fn game_main_thread(stream_list: StreamList, receiver: Receiver<NetworkPacket>, options: Options) {
+pub fn game_main_thread(
+    stream_list: StreamList,
+    receiver: Receiver<NetworkPacket>,
+    options: Options,
+) {
     let mut players: HashMap<SocketAddr, Player> = HashMap::new();
     let mut pools: HashMap<PoolId, Pool> = HashMap::new();
-    let mut pending_pool: HashMap<SocketAddr, ()> = HashMap::new();
+    let mut rooms = Rooms::new(&stream_list);
+    // Players waiting for a game, one queue for each mode and options asked for
+    let mut pending_pools: HashMap<(GameMode, GameOptions), Vec<SocketAddr>> = HashMap::new();
 
     loop {
         thread::sleep(time::Duration::from_millis(10));
@@ -25,8 +56,62 @@ pub fn game_main_thread(stream_list: StreamList, receiver: Receiver<NetworkPacke
             pool.update();
         }
 
+        // Pools left by all their players
+        let empty: Vec<_> = pools
+            .iter()
+            .filter(|(_, pool)| pool.len() == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        for pool_id in empty {
+            let pool = pools.remove(&pool_id).unwrap();
+
+            // Only the spectators are left to hear it
+            pool.close();
+            for spectator in pool.spectators() {
+                if let Some(spectator) = players.get_mut(spectator) {
+                    spectator.change_pool(PoolState::None);
+                }
+            }
+        }
+
+        // Ended matches start again once everyone voted for a rematch, or close after a while
+        let ended: Vec<_> = pools
+            .iter()
+            .filter(|(_, pool)| pool.rematch_ready() || pool.rematch_expired())
+            .map(|(id, _)| *id)
+            .collect();
+        for pool_id in ended {
+            let pool = pools.remove(&pool_id).unwrap();
+
+            if pool.rematch_ready() {
+                let (members, mode, game_options) = pool.rematch();
+                let (id, mut new_pool) = Pool::create(
+                    &mut players,
+                    &stream_list,
+                    members,
+                    mode,
+                    &game_options,
+                    &options,
+                );
+                for spectator in pool.spectators() {
+                    if let Some(player) = players.get_mut(spectator) {
+                        new_pool.add_spectator(*spectator);
+                        player.change_pool(PoolState::Spectating(id));
+                    }
+                }
+                pools.insert(id, new_pool);
+            } else {
+                pool.close();
+                for addr in pool.members().iter().chain(pool.spectators()) {
+                    if let Some(player) = players.get_mut(addr) {
+                        player.change_pool(PoolState::None);
+                    }
+                }
+            }
+        }
+
         for packet in receiver.try_iter() {
-            if let Err(_) = match packet.action {
+            if let Err(reason) = match packet.action {
                 NetworkAction::OpenStream => {
                     players.insert(packet.addr, Player::new());
                     println!("{} opened stream", packet.addr);
@@ -35,58 +120,196 @@ pub fn game_main_thread(stream_list: StreamList, receiver: Receiver<NetworkPacke
                 NetworkAction::CloseStream => {
                     let player = players.get(&packet.addr).unwrap();
 
-                    if let PoolState::Pool(pool_id) = player.pool() {
-                        let pool = pools.get_mut(&pool_id).unwrap();
-
-                        pool.remove_user(&packet.addr);
-                        if pool.len() == 0 {
-                            pools.remove(&pool_id);
+                    if let PoolState::Room(code) = player.pool() {
+                        rooms.leave(&mut players, packet.addr, code);
+                    } else if let PoolState::Pool(pool_id) = player.pool() {
+                        if let Some(pool) = pools.get_mut(&pool_id) {
+                            pool.disconnect(&packet.addr);
+                        }
+                    } else if let PoolState::Spectating(pool_id) = player.pool() {
+                        if let Some(pool) = pools.get_mut(&pool_id) {
+                            pool.remove_spectator(&packet.addr);
                         }
                     } else if player.pool() == PoolState::PendingPool {
-                        pending_pool.remove(&packet.addr);
+                        for pending_pool in pending_pools.values_mut() {
+                            pending_pool.retain(|addr| *addr != packet.addr);
+                        }
                     }
                     players.remove(&packet.addr);
                     println!("{} closed stream", packet.addr);
                     Ok(())
                 }
+                // The handshake is done by the client handler, once per connection
+                NetworkAction::Request(ClientRequest::Hello { .. }) => {
+                    Err(BadRequestReason::UnexpectedHello)
+                }
                 NetworkAction::Request(ClientRequest::SetName(name)) => {
                     let player = players.get_mut(&packet.addr).unwrap();
 
                     if player.pool() != PoolState::None {
-                        Err(())
+                        Err(BadRequestReason::Busy)
                     } else {
                         player.set_name(name);
                         Ok(())
                     }
                 }
-                NetworkAction::Request(ClientRequest::AskForAGame) => {
+                NetworkAction::Request(ClientRequest::AskForAGame(mode, game_options)) => {
                     let player = players.get_mut(&packet.addr).unwrap();
+                    let mode = mode.unwrap_or(options.mode);
 
                     if player.pool() != PoolState::None {
-                        Err(())
+                        Err(BadRequestReason::Busy)
+                    } else if !mode.accepts(&game_options) {
+                        Err(BadRequestReason::InvalidSettings)
                     } else {
                         player.change_pool(PoolState::PendingPool);
-                        pending_pool.insert(packet.addr, ());
-                        if pending_pool.len() == options.pool_size as usize {
-                            let (id, pool) = Pool::create(&mut players, &stream_list, pending_pool);
+                        let pending_pool = pending_pools.entry((mode, game_options)).or_default();
+                        pending_pool.push(packet.addr);
+                        let pool_size = if mode.is_solo() {
+                            1
+                        } else {
+                            options.pool_size as usize
+                        };
+                        if pending_pool.len() == pool_size {
+                            let (id, pool) = Pool::create(
+                                &mut players,
+                                &stream_list,
+                                pending_pools.remove(&(mode, game_options)).unwrap(),
+                                mode,
+                                &game_options,
+                                &options,
+                            );
                             pools.insert(id, pool);
-                            pending_pool = HashMap::new();
                         }
                         Ok(())
                     }
                 }
+                NetworkAction::Request(ClientRequest::CreateRoom(settings)) => {
+                    let player = players.get(&packet.addr).unwrap();
+
+                    if player.pool() != PoolState::None {
+                        Err(BadRequestReason::Busy)
+                    } else {
+                        rooms.create(&mut players, packet.addr, settings)
+                    }
+                }
+                NetworkAction::Request(ClientRequest::JoinRoom(code)) => {
+                    let player = players.get(&packet.addr).unwrap();
+
+                    if player.pool() != PoolState::None {
+                        Err(BadRequestReason::Busy)
+                    } else {
+                        rooms.join(&mut players, packet.addr, code)
+                    }
+                }
+                NetworkAction::Request(ClientRequest::ListRooms) => {
+                    let _ = stream_list
+                        .send_to(&packet.addr, ServerRequest::RoomList(rooms.list(&players)));
+                    Ok(())
+                }
+                NetworkAction::Request(ClientRequest::LeaveRoom) => {
+                    let player = players.get(&packet.addr).unwrap();
+
+                    if let PoolState::Room(code) = player.pool() {
+                        rooms.leave(&mut players, packet.addr, code);
+                        Ok(())
+                    } else {
+                        Err(BadRequestReason::NotInRoom)
+                    }
+                }
+                NetworkAction::Request(ClientRequest::StartRoom) => {
+                    let player = players.get(&packet.addr).unwrap();
+
+                    if let PoolState::Room(code) = player.pool() {
+                        rooms
+                            .start(packet.addr, code)
+                            .map(|(members, mode, game_options)| {
+                                let (id, pool) = Pool::create(
+                                    &mut players,
+                                    &stream_list,
+                                    members,
+                                    mode,
+                                    &game_options,
+                                    &options,
+                                );
+                                pools.insert(id, pool);
+                            })
+                    } else {
+                        Err(BadRequestReason::NotInRoom)
+                    }
+                }
+                NetworkAction::Request(ClientRequest::ListPools) => {
+                    let mut list: Vec<_> = pools.iter().map(|(id, pool)| pool.infos(*id)).collect();
+                    list.sort_by_key(|infos| infos.id);
+                    let _ = stream_list.send_to(&packet.addr, ServerRequest::PoolList(list));
+                    Ok(())
+                }
+                NetworkAction::Request(ClientRequest::Spectate(pool_id)) => {
+                    let player = players.get_mut(&packet.addr).unwrap();
+
+                    match pools.get_mut(&pool_id) {
+                        _ if player.pool() != PoolState::None => Err(BadRequestReason::Busy),
+                        Some(pool) => {
+                            pool.add_spectator(packet.addr);
+                            player.change_pool(PoolState::Spectating(pool_id));
+                            Ok(())
+                        }
+                        None => Err(BadRequestReason::UnknownPool),
+                    }
+                }
                 NetworkAction::Request(ClientRequest::Input(input)) => {
                     let player = players.get_mut(&packet.addr).unwrap();
 
                     if let PoolState::Pool(id) = player.pool() {
-                        let pool = pools.get_mut(&id).unwrap();
-
-                        pool.handle_player_input(&packet.addr, input);
+                        if let Some(pool) = pools.get_mut(&id) {
+                            pool.handle_player_input(&packet.addr, input);
+                        }
                     }
                     Ok(())
                 }
+                NetworkAction::Request(ClientRequest::Rematch) => {
+                    let player = players.get(&packet.addr).unwrap();
+
+                    match player.pool() {
+                        PoolState::Pool(id) => pools
+                            .get_mut(&id)
+                            .ok_or(BadRequestReason::NotInPool)
+                            .and_then(|pool| pool.vote_rematch(&packet.addr)),
+                        _ => Err(BadRequestReason::NotInPool),
+                    }
+                }
+                NetworkAction::Request(ClientRequest::Resume(token)) => {
+                    let player = players.get(&packet.addr).unwrap();
+
+                    match pools.iter_mut().find(|(_, pool)| pool.has_session(token)) {
+                        _ if player.pool() != PoolState::None => Err(BadRequestReason::Busy),
+                        Some((id, pool)) => {
+                            pool.resume(token, packet.addr).map(|(name, old_addr)| {
+                                // The old connection may still be open, it plays no more
+                                if let Some(old_player) = players.get_mut(&old_addr) {
+                                    old_player.change_pool(PoolState::None);
+                                }
+                                let player = players.get_mut(&packet.addr).unwrap();
+                                player.set_name(name);
+                                player.change_pool(PoolState::Pool(*id));
+                            })
+                        }
+                        None => Err(BadRequestReason::UnknownSession),
+                    }
+                }
+                NetworkAction::Request(ClientRequest::SetTarget(target)) => {
+                    let player = players.get_mut(&packet.addr).unwrap();
+
+                    match player.pool() {
+                        PoolState::Pool(id) => pools
+                            .get_mut(&id)
+                            .ok_or(BadRequestReason::NotInPool)
+                            .and_then(|pool| pool.set_target(&packet.addr, target)),
+                        _ => Err(BadRequestReason::NotInPool),
+                    }
+                }
             } {
-                let _ = stream_list.send_to(&packet.addr, ServerRequest::BadRequest);
+                let _ = stream_list.send_to(&packet.addr, ServerRequest::BadRequest(reason));
             }
         }
     }
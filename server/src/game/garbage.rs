@@ -0,0 +1,160 @@
+use rand::Rng;
+use std::str::FromStr;
+use std::time::Duration;
+
+use fetris_protocol::rng::GameRng;
+
+const MATRIX_WIDTH: usize = 10;
+pub const DEFAULT_MESSINESS: u8 = 30;
+
+// Survival garbage rises every 4s at first, 15% faster every 30s, up to a row every 0.5s
+const SURVIVAL_FIRST_RISE_MS: f64 = 4000.;
+const SURVIVAL_MIN_RISE_MS: f64 = 500.;
+const SURVIVAL_SPEED_UP_SECS: u64 = 30;
+const SURVIVAL_SPEED_UP: f64 = 0.85;
+
+// Where the holes of the garbage lines sent to a player are
+#[derive(Clone, Copy, PartialEq)]
+pub enum GarbageMode {
+    // Every line of an attack shares its hole, each attack gets a new one
+    Clean,
+    // Each line has this percentage of chances to move the hole of the previous one
+    Messy(u8),
+    // Every line has its own hole
+    Cheese,
+}
+
+impl FromStr for GarbageMode {
+    type Err = ();
+
+    // A mode name, messy optionally followed by its percentage: "messy:50"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("messy", messiness)) => match messiness.parse() {
+                Ok(messiness) if messiness <= 100 => Ok(Self::Messy(messiness)),
+                _ => Err(()),
+            },
+            Some(_) => Err(()),
+            None => match s {
+                "clean" => Ok(Self::Clean),
+                "messy" => Ok(Self::Messy(DEFAULT_MESSINESS)),
+                "cheese" => Ok(Self::Cheese),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+// Time between two survival garbage rows after surviving for elapsed
+pub fn survival_rise_interval(elapsed: Duration) -> Duration {
+    let speed_ups = (elapsed.as_secs() / SURVIVAL_SPEED_UP_SECS) as i32;
+    let rise_ms = SURVIVAL_FIRST_RISE_MS * SURVIVAL_SPEED_UP.powi(speed_ups);
+
+    Duration::from_millis(rise_ms.max(SURVIVAL_MIN_RISE_MS) as u64)
+}
+
+fn other_column(rng: &mut GameRng, column: usize) -> usize {
+    (column + rng.gen_range(1, MATRIX_WIDTH)) % MATRIX_WIDTH
+}
+
+impl GarbageMode {
+    fn hole_moves(self, rng: &mut GameRng, first_line: bool) -> bool {
+        match self {
+            Self::Clean => first_line,
+            Self::Messy(messiness) => rng.gen_range(0, 100) < messiness,
+            Self::Cheese => true,
+        }
+    }
+
+    // Holes of an attack as (lines, hole position) runs, following the last hole the player got
+    pub fn generate(
+        self,
+        rng: &mut GameRng,
+        lines: u32,
+        last_hole: Option<usize>,
+    ) -> Vec<(u32, usize)> {
+        let mut runs: Vec<(u32, usize)> = Vec::new();
+        let mut hole = last_hole;
+
+        for line in 0..lines {
+            let new_hole = match hole {
+                None => rng.gen_range(0, MATRIX_WIDTH),
+                Some(hole) if self.hole_moves(rng, line == 0) => other_column(rng, hole),
+                Some(hole) => hole,
+            };
+            match runs.last_mut() {
+                Some((run_lines, run_hole)) if *run_hole == new_hole => *run_lines += 1,
+                _ => runs.push((1, new_hole)),
+            }
+            hole = Some(new_hole);
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fetris_protocol::rng::rng_from_seed;
+
+    // Hole of every line, from the runs of an attack
+    fn holes(mode: GarbageMode, lines: u32, last_hole: Option<usize>) -> Vec<usize> {
+        let mut rng = rng_from_seed(42);
+        mode.generate(&mut rng, lines, last_hole)
+            .iter()
+            .flat_map(|(lines, hole)| (0..*lines).map(move |_| *hole))
+            .collect()
+    }
+
+    // Share of the lines whose hole is not the one of the line before
+    fn moves(holes: &[usize], last_hole: usize) -> f64 {
+        let mut previous = last_hole;
+        let mut moves = 0;
+        for hole in holes {
+            if *hole != previous {
+                moves += 1;
+            }
+            previous = *hole;
+        }
+        moves as f64 / holes.len() as f64
+    }
+
+    #[test]
+    fn clean_attacks_share_a_new_hole() {
+        let attack = holes(GarbageMode::Clean, 4, Some(3));
+        assert_eq!(attack.len(), 4);
+        assert_ne!(attack[0], 3);
+        assert!(attack.iter().all(|hole| *hole == attack[0]));
+
+        assert_eq!(holes(GarbageMode::Clean, 4, None).len(), 4);
+    }
+
+    #[test]
+    fn cheese_moves_the_hole_on_every_line() {
+        let attack = holes(GarbageMode::Cheese, 1000, Some(3));
+        assert_eq!(attack.len(), 1000);
+        assert_eq!(moves(&attack, 3), 1.);
+    }
+
+    #[test]
+    fn messy_moves_the_hole_by_its_percentage() {
+        assert_eq!(holes(GarbageMode::Messy(0), 100, Some(3)), vec![3; 100]);
+        assert_eq!(moves(&holes(GarbageMode::Messy(100), 100, Some(3)), 3), 1.);
+
+        let attack = holes(GarbageMode::Messy(30), 10_000, Some(3));
+        let ratio = moves(&attack, 3);
+        assert!(ratio > 0.27 && ratio < 0.33, "{} of the lines moved", ratio);
+        assert!(attack.iter().all(|hole| *hole < MATRIX_WIDTH));
+    }
+
+    #[test]
+    fn messiness_is_read_from_the_mode() {
+        assert!(matches!("messy:50".parse(), Ok(GarbageMode::Messy(50))));
+        assert!(matches!(
+            "messy".parse(),
+            Ok(GarbageMode::Messy(DEFAULT_MESSINESS))
+        ));
+        assert!("messy:101".parse::<GarbageMode>().is_err());
+        assert!("cheese:50".parse::<GarbageMode>().is_err());
+    }
+}
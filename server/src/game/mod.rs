@@ -1,5 +1,9 @@
+pub mod attack;
 mod game;
+pub mod garbage;
 pub mod players;
 pub mod pools;
+pub mod rooms;
+pub mod targeting;
 
 pub use game::*;
@@ -1,298 +1,980 @@
 use rand::{self, Rng};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::{Duration, Instant};
 
-use fetris_protocol::game::{Direction, GameAction, Input, PlayerGame, PlayerMinimalInfos};
-use fetris_protocol::ServerRequest;
-
-use crate::consts::CALL_EVERY_MS;
+use fetris_protocol::game::{
+    Direction, GameAction, GameSettings, Input, PlayerGame, PlayerId, PlayerMinimalInfos,
+    SessionToken,
+};
+use fetris_protocol::game_mode::{GameMode, GameOptions, GameStats};
+use fetris_protocol::gravity::GravityCurve;
+use fetris_protocol::lock_delay::{LockDelay, LockDelaySettings};
+use fetris_protocol::pool::{MatchResult, PoolId, PoolInfos};
+use fetris_protocol::rng::{derive_seed, rng_from_seed, GameRng};
+use fetris_protocol::room::RoomCode;
+use fetris_protocol::scoring::LineClear;
+use fetris_protocol::{BadRequestReason, ServerRequest};
+
+use crate::game::attack::AttackTable;
+use crate::game::garbage::{survival_rise_interval, GarbageMode};
 use crate::game::players::Player;
+use crate::game::targeting::Targeting;
+use crate::game::Options;
 use crate::network::StreamList;
 
-pub type PoolId = Instant;
+// How long the players of an ended match have to vote for a rematch
+const REMATCH_TIMEOUT: Duration = Duration::from_secs(30);
+// How long the game of a disconnected player waits for it to resume
+const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+// How late the Fall of a client can be before the server applies the gravity itself
+const FALL_TOLERANCE: Duration = Duration::from_millis(500);
+// How early a client Fall can arrive on average, for the network jitter
+const EARLY_FALL_TOLERANCE: Duration = Duration::from_millis(100);
+// Client Falls the cadence is averaged over, a single one arriving early is only jitter
+const FALL_CADENCE_SAMPLES: usize = 10;
+// Inputs other than Fall a human can send in a second, key repeat included
+const MAX_INPUTS_PER_SECOND: u32 = 50;
+// Refused inputs in the violation window before the client is considered as cheating
+const MAX_VIOLATIONS: usize = 100;
+const VIOLATION_WINDOW: Duration = Duration::from_secs(60);
+// The pieces and the garbage holes of a pool are drawn from different streams of its seed
+const PIECES_STREAM: u64 = 1;
+const GARBAGE_STREAM: u64 = 2;
 
 fn generate_pool_id() -> PoolId {
-    Instant::now()
+    static NEXT_POOL_ID: AtomicU32 = AtomicU32::new(1);
+    NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+enum InputCheck {
+    Accepted,
+    // Late Fall of a client whose tetrimino the server gravity already moved
+    Skipped,
+    Refused(&'static str),
 }
 
 pub struct PlayerInfos {
     pub player: PlayerGame,
-    pub last_call: Instant,
+    // Last request of the client, any input counts
+    pub last_input: Instant,
+    // Last time the tetrimino fell, on a client Fall or on the server gravity
+    pub last_fall: Instant,
+    pub last_server_fall: Option<Instant>,
+    // Arrival of the last accepted client Falls, oldest first
+    pub client_falls: VecDeque<Instant>,
     pub garbage_received: u32,
+    // Attacks waiting for the next non-clearing placement, as (lines, hole position)
+    pub garbage_queue: VecDeque<(u32, usize)>,
+    // Hole of the last garbage line queued, the next attack's holes follow from it
+    pub last_hole: Option<usize>,
     pub dead: bool,
+    // Reached the goal of the game mode
+    pub finished: bool,
+    pub lock_delay: LockDelay,
+    // Last opponent this player attacked
+    pub target: Option<SocketAddr>,
+    // Opponent chosen by the player with SetTarget, takes over the pool targeting
+    pub manual_target: Option<PlayerId>,
+    // Last opponent who attacked this player, credited with the KO on death
+    pub last_attacker: Option<SocketAddr>,
+    pub kos: u32,
+    pub token: SessionToken,
+    pub disconnected_at: Option<Instant>,
+    // Start of the current second of inputs, and the inputs received in it
+    pub inputs_second: Instant,
+    pub inputs_in_second: u32,
+    // Refused inputs of the violation window, oldest first
+    pub violations: VecDeque<Instant>,
+    // A Resync answered the inputs refused since the last accepted one
+    pub resync_sent: bool,
 }
 
 impl PlayerInfos {
-    pub fn new(player: PlayerGame) -> Self {
+    pub fn new(player: PlayerGame, lock_delay: LockDelaySettings) -> Self {
         Self {
             player,
-            last_call: Instant::now(),
+            last_input: Instant::now(),
+            last_fall: Instant::now(),
+            last_server_fall: None,
+            client_falls: VecDeque::new(),
             garbage_received: 0,
+            garbage_queue: VecDeque::new(),
+            last_hole: None,
             dead: false,
+            finished: false,
+            lock_delay: LockDelay::new(lock_delay),
+            target: None,
+            manual_target: None,
+            last_attacker: None,
+            kos: 0,
+            token: rand::thread_rng().gen(),
+            disconnected_at: None,
+            inputs_second: Instant::now(),
+            inputs_in_second: 0,
+            violations: VecDeque::new(),
+            resync_sent: false,
+        }
+    }
+
+    // Refuses the inputs coming faster than a human or the gravity allows
+    fn check_input(&mut self, input: Input) -> InputCheck {
+        let now = Instant::now();
+        if input == Input::Fall {
+            let call_every = Duration::from_millis(self.player.gravity().call_every_ms.into());
+            if let Some(last_server_fall) = self.last_server_fall {
+                if now.duration_since(last_server_fall) < call_every {
+                    return InputCheck::Skipped;
+                }
+            }
+            if self.client_falls.len() >= FALL_CADENCE_SAMPLES {
+                let first = self.client_falls[self.client_falls.len() - FALL_CADENCE_SAMPLES];
+                let average = now.duration_since(first) / FALL_CADENCE_SAMPLES as u32;
+                if average + EARLY_FALL_TOLERANCE < call_every {
+                    return InputCheck::Refused("Falls sent faster than the gravity");
+                }
+            }
+            self.client_falls.push_back(now);
+            if self.client_falls.len() > FALL_CADENCE_SAMPLES {
+                self.client_falls.pop_front();
+            }
+            return InputCheck::Accepted;
+        }
+
+        if now.duration_since(self.inputs_second) >= Duration::from_secs(1) {
+            self.inputs_second = now;
+            self.inputs_in_second = 0;
+        }
+        self.inputs_in_second += 1;
+        if self.inputs_in_second > MAX_INPUTS_PER_SECOND {
+            return InputCheck::Refused("too many inputs per second");
         }
+        InputCheck::Accepted
+    }
+
+    // Counts a refused input, returns the violations of the window
+    fn add_violation(&mut self) -> usize {
+        let now = Instant::now();
+        self.violations.push_back(now);
+        while let Some(first) = self.violations.front() {
+            if now.duration_since(*first) < VIOLATION_WINDOW {
+                break;
+            }
+            self.violations.pop_front();
+        }
+        self.violations.len()
     }
 }
 
 pub struct Pool<'a> {
     players: HashMap<SocketAddr, PlayerInfos>,
+    spectators: Vec<SocketAddr>,
     stream_list: &'a StreamList,
-    call_every: Duration,
+    settings: GameSettings,
+    game_options: GameOptions,
+    rng: GameRng,
+    attack_table: AttackTable,
+    garbage_mode: GarbageMode,
+    started_at: Instant,
+    gravity_curve: GravityCurve,
+    gravity_timer: Option<Duration>,
+    targeting: Targeting,
+    dig_rise: Option<Duration>,
+    last_garbage_rise: Instant,
+    idle_timeout: Option<Duration>,
+    // Players out of the match, in the order they were eliminated
+    eliminated: Vec<PlayerMinimalInfos>,
+    ended_at: Option<Instant>,
+    rematch_votes: HashSet<SocketAddr>,
 }
 
 impl<'a> Pool<'a> {
     pub fn create(
         players: &mut HashMap<SocketAddr, Player>,
         stream_list: &'a StreamList,
-        pool_sockets: HashMap<SocketAddr, ()>,
+        mut pool_sockets: Vec<SocketAddr>,
+        mode: GameMode,
+        game_options: &GameOptions,
+        options: &Options,
     ) -> (PoolId, Self) {
         let id = generate_pool_id();
+        let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let settings = GameSettings {
+            mode,
+            gravity_curve: options.gravity_curve,
+            randomizer: options.randomizer,
+            lines_per_level: options.lines_per_level,
+            lock_delay: options.lock_delay,
+        };
+        let mut rng = rng_from_seed(derive_seed(seed, GARBAGE_STREAM));
+        let starting_garbage = match mode {
+            GameMode::Dig(rows) => GarbageMode::Cheese.generate(&mut rng, rows, None),
+            _ => Vec::new(),
+        };
         let mut pool_players = HashMap::new();
-        for (socket, _) in pool_sockets.iter() {
+        pool_sockets.sort();
+        for (player_id, socket) in pool_sockets.iter().enumerate() {
             let player = players.get_mut(socket).unwrap();
-            let player_game = PlayerGame::new(player.name().into());
+            let mut player_game = PlayerGame::new(
+                player_id as PlayerId,
+                player.name().into(),
+                derive_seed(seed, PIECES_STREAM),
+                &settings,
+                game_options,
+            );
+            for (lines, hole_position) in starting_garbage.iter() {
+                for _ in 0..*lines {
+                    player_game.add_garbage(*hole_position);
+                }
+            }
 
+            let player_infos = PlayerInfos::new(player_game, options.lock_delay);
             let _ = stream_list.send_to(
                 socket,
-                ServerRequest::GameReady(player_game.clone(), CALL_EVERY_MS),
+                ServerRequest::GameReady(
+                    player_infos.player.clone(),
+                    settings.clone(),
+                    player_infos.token,
+                ),
             );
-            pool_players.insert(socket.clone(), PlayerInfos::new(player_game));
+            pool_players.insert(socket.clone(), player_infos);
             player.change_pool(PoolState::Pool(id));
         }
 
         let pool = Self {
             players: pool_players,
+            spectators: Vec::new(),
             stream_list,
-            call_every: Duration::from_millis(CALL_EVERY_MS.into()),
+            settings,
+            game_options: *game_options,
+            rng,
+            attack_table: options.attack_table,
+            garbage_mode: options.garbage_mode,
+            started_at: Instant::now(),
+            gravity_curve: options.gravity_curve,
+            gravity_timer: options.gravity_timer.map(Duration::from_secs),
+            targeting: options.targeting,
+            dig_rise: options.dig_rise.map(Duration::from_secs),
+            last_garbage_rise: Instant::now(),
+            idle_timeout: options.idle_timeout.map(Duration::from_secs),
+            eliminated: Vec::new(),
+            ended_at: None,
+            rematch_votes: HashSet::new(),
         };
 
-        stream_list.send_to_all(ServerRequest::PlayerListUpdate(pool.user_list()));
+        pool.send_to_pool(ServerRequest::PlayerListUpdate(pool.user_list()));
+        for addr in pool.players.keys() {
+            pool.send_snapshot(addr);
+        }
 
         (id, pool)
     }
 
     pub fn remove_user(&mut self, socket: &SocketAddr) {
+        if self.playing_players().contains(socket) {
+            self.eliminated.push(self.minimal_infos(socket));
+        }
         self.players.remove(socket);
+        self.rematch_votes.remove(socket);
+        self.check_match_end();
     }
 
-    pub fn user_list(&self) -> Vec<PlayerMinimalInfos> {
+    // A player still in the match keeps its game for a while, waiting for a Resume
+    pub fn disconnect(&mut self, socket: &SocketAddr) {
+        // The game was taken over by a Resume from another connection
+        if !self.players.contains_key(socket) {
+            return;
+        }
+        if self.ended_at.is_none() && self.playing_players().contains(socket) {
+            self.players.get_mut(socket).unwrap().disconnected_at = Some(Instant::now());
+            println!("{} disconnected, waiting for it to resume", socket);
+        } else {
+            self.remove_user(socket);
+        }
+    }
+
+    pub fn has_session(&self, token: SessionToken) -> bool {
         self.players
             .values()
-            .map(|elem| PlayerMinimalInfos {
-                name: elem.player.name().to_string(),
-                dead: elem.dead,
-            })
-            .collect()
+            .any(|player| player.token == token && !player.dead && !player.finished)
+    }
+
+    // Moves the game to the new connection and sends it back, returns the name of the player.
+    // The old connection may not be closed yet on a network blip, it is then shut down.
+    // Gives the name of the resumed player and the address it played from
+    pub fn resume(
+        &mut self,
+        token: SessionToken,
+        addr: SocketAddr,
+    ) -> Result<(String, SocketAddr), BadRequestReason> {
+        let old_addr = *self
+            .players
+            .iter()
+            .find(|(_, player)| player.token == token && !player.dead && !player.finished)
+            .ok_or(BadRequestReason::UnknownSession)?
+            .0;
+        let mut player = self.players.remove(&old_addr).unwrap();
+        if player.disconnected_at.is_none() {
+            self.stream_list.shutdown_stream(&old_addr);
+        }
+
+        player.disconnected_at = None;
+        for other in self.players.values_mut() {
+            if other.target == Some(old_addr) {
+                other.target = Some(addr);
+            }
+            if other.last_attacker == Some(old_addr) {
+                other.last_attacker = Some(addr);
+            }
+        }
+        let _ = self.stream_list.send_to(
+            &addr,
+            ServerRequest::GameReady(player.player.clone(), self.settings.clone(), token),
+        );
+        for other in self.players.values() {
+            let _ = self
+                .stream_list
+                .send_to(&addr, ServerRequest::PlayerSnapshot(other.player.clone()));
+        }
+        let name = player.player.name().to_string();
+        self.players.insert(addr, player);
+        let _ = self
+            .stream_list
+            .send_to(&addr, ServerRequest::PlayerListUpdate(self.user_list()));
+        println!("{} resumed the game of {}", addr, old_addr);
+        Ok((name, old_addr))
     }
 
-    pub fn send_garbage(&mut self, sender: &SocketAddr, row_broken: u32, is_t_spin: bool) {
-        let garbage_to_send = match (is_t_spin, row_broken) {
-            (false, 2) => 1,
-            (false, 3) => 2,
-            (false, 4) => 4,
-            (true, x) => 2 * x,
-            (_, _) => 0,
+    fn minimal_infos(&self, addr: &SocketAddr) -> PlayerMinimalInfos {
+        let elem = &self.players[addr];
+
+        PlayerMinimalInfos {
+            id: elem.player.id(),
+            name: elem.player.name().to_string(),
+            dead: elem.dead,
+            kos: elem.kos,
+        }
+    }
+
+    pub fn user_list(&self) -> Vec<PlayerMinimalInfos> {
+        let mut list: Vec<_> = self
+            .players
+            .keys()
+            .map(|addr| self.minimal_infos(addr))
+            .collect();
+        list.sort_by_key(|elem| elem.id);
+        list
+    }
+
+    pub fn infos(&self, id: PoolId) -> PoolInfos {
+        PoolInfos {
+            id,
+            mode: self.settings.mode,
+            players: self.user_list(),
+        }
+    }
+
+    pub fn spectators(&self) -> &[SocketAddr] {
+        &self.spectators
+    }
+
+    pub fn add_spectator(&mut self, addr: SocketAddr) {
+        let mut games: Vec<_> = self
+            .players
+            .values()
+            .map(|player| player.player.clone())
+            .collect();
+        games.sort_by_key(|game| game.id());
+
+        let _ = self.stream_list.send_to(
+            &addr,
+            ServerRequest::SpectateReady(games, self.settings.clone()),
+        );
+        let _ = self
+            .stream_list
+            .send_to(&addr, ServerRequest::PlayerListUpdate(self.user_list()));
+        self.spectators.push(addr);
+    }
+
+    pub fn remove_spectator(&mut self, addr: &SocketAddr) {
+        self.spectators.retain(|spectator| spectator != addr);
+    }
+
+    fn send_to_pool(&self, request: ServerRequest) {
+        for addr in self.players.keys().chain(self.spectators.iter()) {
+            let _ = self.stream_list.send_to(addr, request.clone());
+        }
+    }
+
+    // Everyone watching the board of this player: its opponents and the spectators
+    fn send_to_others(&self, addr: &SocketAddr, request: ServerRequest) {
+        let others = self.players.keys().filter(|other| *other != addr);
+        for other in others.chain(self.spectators.iter()) {
+            let _ = self.stream_list.send_to(other, request.clone());
+        }
+    }
+
+    // The player gets its own actions, the others get them tagged with its id
+    fn send_action(&self, addr: &SocketAddr, action: GameAction) {
+        let id = self.players[addr].player.id();
+
+        self.send_to_others(addr, ServerRequest::PlayerAction(id, action.clone()));
+        let _ = self
+            .stream_list
+            .send_to(addr, ServerRequest::MinifiedAction(action));
+    }
+
+    // The whole game, sent once when the pool starts, its actions keep the others up to date
+    fn send_snapshot(&self, addr: &SocketAddr) {
+        self.send_to_others(
+            addr,
+            ServerRequest::PlayerSnapshot(self.players[addr].player.clone()),
+        );
+    }
+
+    pub fn set_target(
+        &mut self,
+        addr: &SocketAddr,
+        target: PlayerId,
+    ) -> Result<(), BadRequestReason> {
+        let player = self.players.get(addr).ok_or(BadRequestReason::NotInPool)?;
+        if player.player.id() == target
+            || !self.players.values().any(|elem| elem.player.id() == target)
+        {
+            return Err(BadRequestReason::InvalidTarget);
+        }
+        self.players.get_mut(addr).unwrap().manual_target = Some(target);
+        Ok(())
+    }
+
+    fn kill_player(&mut self, addr: &SocketAddr) {
+        let player = self.players.get_mut(addr).unwrap();
+        player.dead = true;
+        // Topping out is how solo games without a goal end, they still get their results
+        let request = if self.settings.mode.is_solo() {
+            ServerRequest::GameFinished(GameStats::new(
+                &player.player,
+                Instant::now().duration_since(self.started_at),
+                false,
+            ))
+        } else {
+            ServerRequest::GameOver
         };
+        if let Some(attacker) = player.last_attacker {
+            if let Some(attacker) = self.players.get_mut(&attacker) {
+                attacker.kos += 1;
+            }
+        }
+        let _ = self.stream_list.send_to(addr, request);
+        self.send_to_pool(ServerRequest::PlayerListUpdate(self.user_list()));
+        self.eliminated.push(self.minimal_infos(addr));
+        println!("{} is dead", addr);
+        self.check_match_end();
+    }
 
-        if garbage_to_send == 0 {
+    fn finish_player(&mut self, addr: &SocketAddr) {
+        let player = self.players.get_mut(addr).unwrap();
+        let stats = GameStats::new(
+            &player.player,
+            Instant::now().duration_since(self.started_at),
+            true,
+        );
+
+        player.finished = true;
+        println!("{} finished in {}ms", addr, stats.time_ms);
+        let _ = self
+            .stream_list
+            .send_to(addr, ServerRequest::GameFinished(stats));
+        self.check_match_end();
+    }
+
+    // A versus match ends with the last player standing, a solo one with its player
+    fn check_match_end(&mut self) {
+        // Disconnected players cannot win, they forfeit if the match ends without them
+        let (standing, offline): (Vec<_>, Vec<_>) = self
+            .playing_players()
+            .into_iter()
+            .partition(|addr| self.players[addr].disconnected_at.is_none());
+        let last_standing = if self.settings.mode.is_solo() { 0 } else { 1 };
+        if self.ended_at.is_some() || standing.len() > last_standing {
             return;
         }
 
-        let mut receiver: Option<SocketAddr> = None;
-        let mut last_receiver_garbage = 0;
+        self.ended_at = Some(Instant::now());
+        if self.settings.mode.is_solo() {
+            return;
+        }
+        let mut placements = Vec::new();
+        for addr in standing {
+            self.players.get_mut(&addr).unwrap().finished = true;
+            println!("{} won", addr);
+            placements.push(self.minimal_infos(&addr));
+        }
+        placements.extend(self.eliminated.iter().rev().cloned());
+        for addr in offline {
+            self.players.get_mut(&addr).unwrap().dead = true;
+            placements.push(self.minimal_infos(&addr));
+        }
+        self.send_to_pool(ServerRequest::MatchResult(MatchResult { placements }));
+    }
 
-        for (addr, player) in self.players.iter() {
-            if addr == sender || player.dead {
-                continue;
+    pub fn vote_rematch(&mut self, addr: &SocketAddr) -> Result<(), BadRequestReason> {
+        if !self.players.contains_key(addr) {
+            return Err(BadRequestReason::NotInPool);
+        }
+        if self.ended_at.is_none() {
+            return Err(BadRequestReason::MatchNotOver);
+        }
+        self.rematch_votes.insert(*addr);
+        self.send_to_pool(ServerRequest::RematchVotes(
+            self.rematch_votes.len() as u32,
+            self.players.len() as u32,
+        ));
+        Ok(())
+    }
+
+    // Everyone left voted, and there are still enough players for the mode
+    pub fn rematch_ready(&self) -> bool {
+        let enough_players = self.settings.mode.is_solo() || self.players.len() > 1;
+        self.ended_at.is_some() && enough_players && self.rematch_votes.len() == self.players.len()
+    }
+
+    pub fn rematch_expired(&self) -> bool {
+        match self.ended_at {
+            Some(ended_at) => Instant::now().duration_since(ended_at) >= REMATCH_TIMEOUT,
+            None => false,
+        }
+    }
+
+    // What the next pool needs to replay the match
+    pub fn rematch(&self) -> (Vec<SocketAddr>, GameMode, GameOptions) {
+        (self.members(), self.settings.mode, self.game_options)
+    }
+
+    pub fn members(&self) -> Vec<SocketAddr> {
+        self.players.keys().copied().collect()
+    }
+
+    pub fn close(&self) {
+        self.send_to_pool(ServerRequest::PoolClosed);
+    }
+
+    fn update_garbage_meter(&mut self, addr: &SocketAddr) {
+        let player = self.players.get_mut(addr).unwrap();
+        let pending_garbage = player.garbage_queue.iter().map(|(lines, _)| lines).sum();
+
+        player.player.set_pending_garbage(pending_garbage);
+        // The meter is only shown to its player
+        let _ = self
+            .stream_list
+            .send_to(addr, ServerRequest::PendingGarbage(pending_garbage));
+    }
+
+    // Garbage coming from the game mode rather than an opponent, inserted right away
+    fn raise_garbage(&mut self, addr: &SocketAddr, lines: u32) {
+        let player = self.players.get_mut(addr).unwrap();
+        let holes = self
+            .garbage_mode
+            .generate(&mut self.rng, lines, player.last_hole);
+
+        player.last_hole = holes.last().map(|(_, hole)| *hole);
+        player.garbage_queue.extend(holes);
+        self.insert_pending_garbage(addr);
+    }
+
+    fn insert_pending_garbage(&mut self, addr: &SocketAddr) {
+        let player = self.players.get_mut(addr).unwrap();
+        if player.garbage_queue.is_empty() {
+            return;
+        }
+
+        let mut actions = Vec::new();
+        while let Some((garbage, hole_position)) = player.garbage_queue.pop_front() {
+            for _ in 0..garbage {
+                player.player.add_garbage(hole_position);
             }
-            if receiver.is_none() || last_receiver_garbage > player.garbage_received {
-                receiver = Some(addr.clone());
-                last_receiver_garbage = player.garbage_received;
+            actions.push(GameAction::GetGarbage(garbage, hole_position));
+        }
+        // The rows moved the stack under the tetrimino
+        player
+            .lock_delay
+            .update_ground(&player.player, Instant::now());
+        for action in actions {
+            self.send_action(addr, action);
+        }
+        self.update_garbage_meter(addr);
+    }
+
+    // Line clears cancel the pending garbage first, what's left is sent to an opponent
+    fn handle_placement(&mut self, addr: &SocketAddr, clear: &LineClear) {
+        if clear.lines() == 0 {
+            self.insert_pending_garbage(addr);
+            return;
+        }
+
+        let mut attack = self.attack_table.attack(clear);
+        let player = self.players.get_mut(addr).unwrap();
+        let cancelled = attack > 0 && !player.garbage_queue.is_empty();
+
+        while attack > 0 {
+            if let Some((garbage, _)) = player.garbage_queue.front_mut() {
+                let cancelled_lines = attack.min(*garbage);
+                *garbage -= cancelled_lines;
+                attack -= cancelled_lines;
+                if *garbage == 0 {
+                    player.garbage_queue.pop_front();
+                }
+            } else {
+                break;
             }
         }
+        if cancelled {
+            self.update_garbage_meter(addr);
+        }
 
-        println!(
-            "Garbage to send: {}, Receiver: {:?}, t-spin: {}",
-            garbage_to_send, receiver, is_t_spin
-        );
+        // Players racing each other in a solo mode room play on their own
+        if !self.settings.mode.is_solo() {
+            self.send_garbage(addr, attack);
+        }
+    }
+
+    pub fn send_garbage(&mut self, sender: &SocketAddr, garbage_to_send: u32) {
+        if garbage_to_send == 0 {
+            return;
+        }
+
+        let receiver = self.choose_receiver(sender);
 
         if let Some(addr) = receiver {
+            self.players.get_mut(sender).unwrap().target = Some(addr);
             let player = self.players.get_mut(&addr).unwrap();
-            let hole_position = rand::thread_rng().gen_range(0, 10);
+            let holes =
+                self.garbage_mode
+                    .generate(&mut self.rng, garbage_to_send, player.last_hole);
 
+            player.last_attacker = Some(*sender);
             player.garbage_received += garbage_to_send;
-            for _ in 0..garbage_to_send {
-                player.player.add_garbage(hole_position);
+            player.last_hole = holes.last().map(|(_, hole)| *hole);
+            player.garbage_queue.extend(holes);
+            self.update_garbage_meter(&addr);
+        }
+    }
+
+    fn choose_receiver(&mut self, sender: &SocketAddr) -> Option<SocketAddr> {
+        // Sorted by id so that the choice only depends on the pool seed
+        let mut opponents: Vec<_> = self
+            .players
+            .iter()
+            .filter(|(addr, player)| *addr != sender && !player.dead)
+            .map(|(addr, player)| (player.player.id(), *addr))
+            .collect();
+        opponents.sort();
+        let opponents: Vec<_> = opponents.into_iter().map(|(_, addr)| addr).collect();
+        if opponents.is_empty() {
+            return None;
+        }
+
+        if let Some(target) = self.players[sender].manual_target {
+            if let Some(addr) = opponents
+                .iter()
+                .find(|addr| self.players[*addr].player.id() == target)
+            {
+                return Some(*addr);
             }
-            let _ = self.stream_list.send_to(
-                &addr,
-                ServerRequest::MinifiedAction(GameAction::GetGarbage(
-                    garbage_to_send,
-                    hole_position,
-                )),
-            );
+        }
+
+        let even = opponents
+            .iter()
+            .min_by_key(|addr| self.players[*addr].garbage_received)
+            .copied();
+        match self.targeting {
+            Targeting::Even => even,
+            Targeting::Random => Some(opponents[self.rng.gen_range(0, opponents.len())]),
+            Targeting::Attackers => {
+                let attackers: Vec<_> = opponents
+                    .iter()
+                    .filter(|addr| self.players[*addr].target == Some(*sender))
+                    .copied()
+                    .collect();
+                if attackers.is_empty() {
+                    even
+                } else {
+                    Some(attackers[self.rng.gen_range(0, attackers.len())])
+                }
+            }
+            Targeting::Kos => opponents
+                .iter()
+                .max_by_key(|addr| {
+                    let player = &self.players[*addr].player;
+                    player.stack_height() + player.pending_garbage()
+                })
+                .copied(),
+            Targeting::Badges => opponents
+                .iter()
+                .max_by_key(|addr| self.players[*addr].kos)
+                .copied(),
+        }
+    }
+
+    fn gravity_level(&self, player: &PlayerInfos) -> u32 {
+        let level = player.player.score().level();
+
+        if let Some(gravity_timer) = self.gravity_timer {
+            let elapsed = Instant::now().duration_since(self.started_at);
+            level.max(1 + (elapsed.as_secs() / gravity_timer.as_secs()) as u32)
+        } else {
+            level
+        }
+    }
+
+    fn update_gravity(&mut self) {
+        let gravities: Vec<_> = self
+            .players
+            .iter()
+            .filter(|(_, player)| !player.dead && !player.finished)
+            .map(|(addr, player)| {
+                (
+                    *addr,
+                    self.gravity_curve.gravity(self.gravity_level(player)),
+                )
+            })
+            .collect();
+
+        for (addr, gravity) in gravities {
+            let player = self.players.get_mut(&addr).unwrap();
+            if player.player.gravity() != gravity {
+                player.player.set_gravity(gravity);
+                let id = player.player.id();
+                let _ = self
+                    .stream_list
+                    .send_to(&addr, ServerRequest::GravityChanged(gravity));
+                self.send_to_others(&addr, ServerRequest::PlayerGravityChanged(id, gravity));
+            }
+        }
+    }
+
+    // Garbage rows rising on their own in solo modes
+    fn garbage_rise_interval(&self) -> Option<Duration> {
+        match self.settings.mode {
+            GameMode::Dig(_) => self.dig_rise,
+            GameMode::Survival => Some(survival_rise_interval(
+                Instant::now().duration_since(self.started_at),
+            )),
+            _ => None,
         }
     }
 
+    fn playing_players(&self) -> Vec<SocketAddr> {
+        self.players
+            .iter()
+            .filter(|(_, player)| !player.dead && !player.finished)
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
     pub fn update(&mut self) {
-        for (_, player) in self.players.iter_mut() {
-            if player.dead || (Instant::now().duration_since(player.last_call) < self.call_every) {
-                continue;
+        let gone: Vec<_> = self
+            .players
+            .iter()
+            .filter(|(_, player)| match player.disconnected_at {
+                Some(disconnected_at) => {
+                    Instant::now().duration_since(disconnected_at) >= RECONNECT_GRACE
+                }
+                None => false,
+            })
+            .map(|(addr, _)| *addr)
+            .collect();
+        for addr in gone {
+            println!("{} did not resume its game", addr);
+            self.remove_user(&addr);
+        }
+
+        self.update_gravity();
+
+        if let Some(time_limit) = self.settings.mode.time_limit() {
+            if Instant::now().duration_since(self.started_at) >= time_limit {
+                for addr in self.playing_players() {
+                    self.finish_player(&addr);
+                }
+            }
+        }
+
+        if let Some(garbage_rise) = self.garbage_rise_interval() {
+            if Instant::now().duration_since(self.last_garbage_rise) >= garbage_rise {
+                self.last_garbage_rise = Instant::now();
+                for addr in self.playing_players() {
+                    self.raise_garbage(&addr, 1);
+                }
             }
+        }
 
-            if player.player.current_tetrimino().is_none() {
-                // TODO: Verify if the client didn't freezed in a "Non-Death" state and kill it if so
+        // Disconnected players wait for their Resume, their game stays still
+        let connected = self
+            .playing_players()
+            .into_iter()
+            .filter(|addr| self.players[addr].disconnected_at.is_none());
+        for addr in connected.collect::<Vec<_>>() {
+            let player = &self.players[&addr];
+            if let Some(idle_timeout) = self.idle_timeout {
+                if Instant::now().duration_since(player.last_input) >= idle_timeout {
+                    println!("{} is idle", addr);
+                    self.kill_player(&addr);
+                    continue;
+                }
+            }
+
+            // The lock delay runs out on the server clock, not on the next Fall
+            if player.lock_delay.should_lock(Instant::now()) {
+                self.apply_input(&addr, Input::Fall);
+                continue;
             }
 
-            player.last_call = Instant::now();
+            let call_every = Duration::from_millis(player.player.gravity().call_every_ms.into());
+            if Instant::now().duration_since(player.last_fall) >= call_every + FALL_TOLERANCE {
+                self.players.get_mut(&addr).unwrap().last_server_fall = Some(Instant::now());
+                self.apply_input(&addr, Input::Fall);
+            }
         }
     }
 
     pub fn handle_player_input(&mut self, socket: &SocketAddr, input: Input) {
-        let mut garbage: Option<(SocketAddr, u32, bool)> = None;
+        let player = match self.players.get_mut(socket) {
+            Some(player) => player,
+            None => return,
+        };
+        player.last_input = Instant::now();
+        match player.check_input(input) {
+            InputCheck::Accepted => {
+                player.resync_sent = false;
+                self.apply_input(socket, input);
+            }
+            InputCheck::Skipped => self.send_resync(socket),
+            InputCheck::Refused(violation) => {
+                println!("{} refused {:?}: {}", socket, input, violation);
+                if player.add_violation() >= MAX_VIOLATIONS
+                    && self.playing_players().contains(socket)
+                {
+                    println!("{} is cheating, disconnecting it", socket);
+                    self.kill_player(socket);
+                    self.stream_list.shutdown_stream(socket);
+                    return;
+                }
+                self.send_resync(socket);
+            }
+        }
+    }
+
+    // The client predicted an input the server did not apply, it gets the server board back
+    // once for the whole burst of refused inputs
+    fn send_resync(&mut self, addr: &SocketAddr) {
+        let player = self.players.get_mut(addr).unwrap();
+        if player.resync_sent {
+            return;
+        }
+
+        player.resync_sent = true;
+        let _ = self
+            .stream_list
+            .send_to(addr, ServerRequest::Resync(player.player.clone()));
+    }
+
+    // Inputs from the client, and the Fall of the server gravity when the client is late
+    fn apply_input(&mut self, socket: &SocketAddr, input: Input) {
+        let mut garbage: Option<(SocketAddr, LineClear)> = None;
+        let mut action: Option<GameAction> = None;
+        let mut dead = false;
         let player = self.players.get_mut(socket).unwrap();
-        if player.dead {
+        if player.dead || player.finished {
             return;
         }
         match input {
             Input::Left => {
-                let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    if tetrimino.can_move_to(&matrix, Direction::Left) {
-                        tetrimino.apply_direction(Direction::Left);
-                        let _ = self.stream_list.send_to(
-                            &socket,
-                            ServerRequest::MinifiedAction(GameAction::MoveCurrentTetrimino(
-                                Direction::Left,
-                            )),
-                        );
-                    }
-                    player.last_call = Instant::now();
+                if player.player.move_current_tetrimino(Direction::Left) {
+                    player.lock_delay.on_move(Instant::now());
+                    action = Some(GameAction::MoveCurrentTetrimino(Direction::Left));
                 }
             }
             Input::Right => {
-                let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    if tetrimino.can_move_to(&matrix, Direction::Right) {
-                        tetrimino.apply_direction(Direction::Right);
-                        let _ = self.stream_list.send_to(
-                            &socket,
-                            ServerRequest::MinifiedAction(GameAction::MoveCurrentTetrimino(
-                                Direction::Right,
-                            )),
-                        );
-                    }
-                    player.last_call = Instant::now();
+                if player.player.move_current_tetrimino(Direction::Right) {
+                    player.lock_delay.on_move(Instant::now());
+                    action = Some(GameAction::MoveCurrentTetrimino(Direction::Right));
                 }
             }
             Input::FastMove => {
-                let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    while tetrimino.can_move_to(&matrix, Direction::Down) {
-                        tetrimino.apply_direction(Direction::Down);
-                    }
+                if let Some(clear) = player.player.hard_drop() {
+                    garbage = Some((socket.clone(), clear));
 
-                    let is_t_spin = !tetrimino.can_move_to(&matrix, Direction::Left)
-                        && !tetrimino.can_move_to(&matrix, Direction::Right)
-                        && !tetrimino.can_move_to(&matrix, Direction::Up);
-                    let row_broken = player.player.place_current_tetrimino();
-
-                    garbage = Some((socket.clone(), row_broken.len() as u32, is_t_spin));
-
-                    let _ = self.stream_list.send_to(
-                        socket,
-                        ServerRequest::MinifiedAction(GameAction::MoveCurrentTetrimino(
-                            Direction::FastDown,
-                        )),
-                    );
-                    player.last_call = Instant::now();
+                    action = Some(GameAction::MoveCurrentTetrimino(Direction::FastDown));
                 }
             }
             Input::RotateRevert => {
-                let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    if tetrimino.rotate(&matrix, true) {
-                        let _ = self
-                            .stream_list
-                            .send_to(socket, ServerRequest::MinifiedAction(GameAction::Rotate(true)));
-                        player.last_call = Instant::now();
-                    }
+                if player.player.rotate_current_tetrimino(true) {
+                    player.lock_delay.on_move(Instant::now());
+                    action = Some(GameAction::Rotate(true));
                 }
             }
             Input::Rotate => {
-                let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    if tetrimino.rotate(&matrix, false) {
-                        let _ = self
-                            .stream_list
-                            .send_to(socket, ServerRequest::MinifiedAction(GameAction::Rotate(false)));
-                        player.last_call = Instant::now();
-                    }
+                if player.player.rotate_current_tetrimino(false) {
+                    player.lock_delay.on_move(Instant::now());
+                    action = Some(GameAction::Rotate(false));
                 }
             }
             Input::StockTetrimino => {
                 if player.player.current_tetrimino().is_some() {
                     player.player.stock_current_tetrimino();
-                    let _ = self.stream_list.send_to(
-                        socket,
-                        ServerRequest::MinifiedAction(GameAction::StockTetrimino),
-                    );
-                    player.last_call = Instant::now();
+                    player.lock_delay.reset();
+                    action = Some(GameAction::StockTetrimino);
                 }
             }
             Input::Acceleration => {
-                let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    if tetrimino.can_move_to(&matrix, Direction::Down) {
-                        tetrimino.apply_direction(Direction::Down);
-                        let _ = self.stream_list.send_to(
-                            &socket,
-                            ServerRequest::MinifiedAction(GameAction::MoveCurrentTetrimino(
-                                Direction::Down,
-                            )),
-                        );
-                    }
-                    player.last_call = Instant::now();
+                if player.player.soft_drop() {
+                    player
+                        .lock_delay
+                        .on_step_down(&player.player.current_tetrimino().unwrap());
+                    action = Some(GameAction::MoveCurrentTetrimino(Direction::Down));
                 }
             }
             Input::Fall => {
+                player.last_fall = Instant::now();
                 let matrix = player.player.matrix().clone();
-                if let Some(tetrimino) = player.player.current_tetrimino_mut() {
-                    if tetrimino.can_move_to(&matrix, Direction::Down) {
-                        tetrimino.apply_direction(Direction::Down);
-                    } else {
-                        let is_t_spin = !tetrimino.can_move_to(&matrix, Direction::Left)
-                            && !tetrimino.can_move_to(&matrix, Direction::Right)
-                            && !tetrimino.can_move_to(&matrix, Direction::Up);
-                        let row_broken = player.player.place_current_tetrimino();
-
-                        garbage = Some((socket.clone(), row_broken.len() as u32, is_t_spin));
+                if let Some(tetrimino) = player.player.current_tetrimino() {
+                    if tetrimino.can_move_to(&matrix, Direction::Down)
+                        || player.lock_delay.should_lock(Instant::now())
+                    {
+                        if let Some(clear) = player.player.fall() {
+                            garbage = Some((socket.clone(), clear));
+                        } else if let Some(tetrimino) = player.player.current_tetrimino() {
+                            player.lock_delay.on_step_down(&tetrimino);
+                        }
+                        action = Some(GameAction::Fall);
                     }
-                    let _ = self
-                        .stream_list
-                        .send_to(socket, ServerRequest::MinifiedAction(GameAction::Fall));
                 } else {
                     let added_tetrimino = player.player.new_tetrimino();
+                    player.lock_delay.reset();
                     if !player.player.current_tetrimino().unwrap().is_valid(&matrix) {
-                        player.dead = true;
-                        let _ = self.stream_list.send_to(socket, ServerRequest::GameOver);
-                        self.stream_list
-                            .send_to_all(ServerRequest::PlayerListUpdate(self.user_list().clone()));
-                        println!("{} is dead", socket);
+                        dead = true;
                     } else {
-                        let _ = self.stream_list.send_to(
-                            socket,
-                            ServerRequest::MinifiedAction(GameAction::NewTetrimino(
-                                added_tetrimino,
-                            )),
-                        );
+                        action = Some(GameAction::NewTetrimino(added_tetrimino));
                     }
                 }
             }
         }
+        player
+            .lock_delay
+            .update_ground(&player.player, Instant::now());
 
-        if let Some((addr, row_broken, is_t_spin)) = garbage {
-            self.send_garbage(&addr, row_broken, is_t_spin);
+        if let Some(action) = action {
+            self.send_action(socket, action);
+        }
+        if dead {
+            self.kill_player(socket);
+        }
+        if let Some((addr, clear)) = garbage {
+            self.handle_placement(&addr, &clear);
+            if self.settings.mode.goal_reached(&self.players[&addr].player) {
+                self.finish_player(&addr);
+            }
         }
     }
 
@@ -305,5 +987,7 @@ impl<'a> Pool<'a> {
 pub enum PoolState {
     Pool(PoolId),
     PendingPool,
+    Room(RoomCode),
+    Spectating(PoolId),
     None,
 }
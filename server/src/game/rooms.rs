@@ -0,0 +1,186 @@
+use rand::{self, Rng};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use fetris_protocol::game_mode::{GameMode, GameOptions};
+use fetris_protocol::room::{RoomCode, RoomInfos, RoomSettings};
+use fetris_protocol::{BadRequestReason, ServerRequest};
+
+use crate::game::players::Player;
+use crate::game::pools::PoolState;
+use crate::network::StreamList;
+
+const MAX_ROOM_SIZE: u16 = 99;
+
+pub struct Room {
+    settings: RoomSettings,
+    host: SocketAddr,
+    members: Vec<SocketAddr>,
+}
+
+// Rooms waiting for their host to start the match
+pub struct Rooms<'a> {
+    rooms: HashMap<RoomCode, Room>,
+    stream_list: &'a StreamList,
+}
+
+impl<'a> Rooms<'a> {
+    pub fn new(stream_list: &'a StreamList) -> Self {
+        Self {
+            rooms: HashMap::new(),
+            stream_list,
+        }
+    }
+
+    fn generate_code(&self) -> RoomCode {
+        loop {
+            let code = rand::thread_rng().gen_range(100000, 1000000);
+            if !self.rooms.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    fn infos(&self, code: RoomCode, players: &HashMap<SocketAddr, Player>) -> RoomInfos {
+        let room = &self.rooms[&code];
+        let name = |addr: &SocketAddr| players.get(addr).map_or("", |player| player.name());
+
+        RoomInfos {
+            code,
+            name: room.settings.name.clone(),
+            host: name(&room.host).to_string(),
+            players: room
+                .members
+                .iter()
+                .map(|addr| name(addr).to_string())
+                .collect(),
+            size: room.settings.size,
+            mode: room.settings.mode,
+            private: room.settings.private,
+        }
+    }
+
+    fn send_update(&self, code: RoomCode, players: &HashMap<SocketAddr, Player>) {
+        let infos = self.infos(code, players);
+        for addr in self.rooms[&code].members.iter() {
+            let _ = self
+                .stream_list
+                .send_to(addr, ServerRequest::RoomUpdate(infos.clone()));
+        }
+    }
+
+    pub fn create(
+        &mut self,
+        players: &mut HashMap<SocketAddr, Player>,
+        host: SocketAddr,
+        settings: RoomSettings,
+    ) -> Result<(), BadRequestReason> {
+        if settings.name.is_empty()
+            || settings.size < 1
+            || settings.size > MAX_ROOM_SIZE
+            || !settings.mode.accepts(&settings.options)
+        {
+            return Err(BadRequestReason::InvalidSettings);
+        }
+
+        let code = self.generate_code();
+        self.rooms.insert(
+            code,
+            Room {
+                settings,
+                host,
+                members: vec![host],
+            },
+        );
+        players
+            .get_mut(&host)
+            .unwrap()
+            .change_pool(PoolState::Room(code));
+        println!("{} created room {}", host, code);
+        self.send_update(code, players);
+        Ok(())
+    }
+
+    pub fn join(
+        &mut self,
+        players: &mut HashMap<SocketAddr, Player>,
+        addr: SocketAddr,
+        code: RoomCode,
+    ) -> Result<(), BadRequestReason> {
+        let room = self
+            .rooms
+            .get_mut(&code)
+            .ok_or(BadRequestReason::UnknownRoom)?;
+        if room.members.len() >= room.settings.size as usize {
+            return Err(BadRequestReason::RoomFull);
+        }
+
+        room.members.push(addr);
+        players
+            .get_mut(&addr)
+            .unwrap()
+            .change_pool(PoolState::Room(code));
+        self.send_update(code, players);
+        Ok(())
+    }
+
+    // The next member becomes the host when the host leaves, empty rooms are closed
+    pub fn leave(
+        &mut self,
+        players: &mut HashMap<SocketAddr, Player>,
+        addr: SocketAddr,
+        code: RoomCode,
+    ) {
+        if let Some(player) = players.get_mut(&addr) {
+            player.change_pool(PoolState::None);
+        }
+        let room = if let Some(room) = self.rooms.get_mut(&code) {
+            room
+        } else {
+            return;
+        };
+
+        room.members.retain(|member| *member != addr);
+        if room.members.is_empty() {
+            self.rooms.remove(&code);
+            println!("Room {} closed", code);
+            return;
+        }
+        if room.host == addr {
+            room.host = room.members[0];
+        }
+        self.send_update(code, players);
+    }
+
+    // Public rooms only
+    pub fn list(&self, players: &HashMap<SocketAddr, Player>) -> Vec<RoomInfos> {
+        let mut list: Vec<_> = self
+            .rooms
+            .iter()
+            .filter(|(_, room)| !room.settings.private)
+            .map(|(code, _)| self.infos(*code, players))
+            .collect();
+        list.sort_by_key(|infos| infos.code);
+        list
+    }
+
+    // Closes the room, its members are handed over to a new pool
+    pub fn start(
+        &mut self,
+        addr: SocketAddr,
+        code: RoomCode,
+    ) -> Result<(Vec<SocketAddr>, GameMode, GameOptions), BadRequestReason> {
+        match self.rooms.get(&code) {
+            Some(room) if room.host != addr => return Err(BadRequestReason::NotHost),
+            Some(room) if !room.settings.mode.is_solo() && room.members.len() < 2 => {
+                return Err(BadRequestReason::NotEnoughPlayers)
+            }
+            Some(_) => {}
+            None => return Err(BadRequestReason::UnknownRoom),
+        }
+
+        let room = self.rooms.remove(&code).unwrap();
+        println!("Room {} started", code);
+        Ok((room.members, room.settings.mode, room.settings.options))
+    }
+}
@@ -0,0 +1,30 @@
+use std::str::FromStr;
+
+// How the receiver of an attack is chosen when the sender has no manual target
+#[derive(Clone, Copy, PartialEq)]
+pub enum Targeting {
+    // The player who received the least garbage so far
+    Even,
+    Random,
+    // The players currently targeting the sender
+    Attackers,
+    // The player with the highest stack, pending garbage included
+    Kos,
+    // The player with the most KOs
+    Badges,
+}
+
+impl FromStr for Targeting {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "even" => Ok(Self::Even),
+            "random" => Ok(Self::Random),
+            "attackers" => Ok(Self::Attackers),
+            "kos" => Ok(Self::Kos),
+            "badges" => Ok(Self::Badges),
+            _ => Err(()),
+        }
+    }
+}
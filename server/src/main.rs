@@ -1,4 +1,12 @@
 use clap::{App, Arg};
+use fetris_protocol::game_mode::{
+    GameMode, DEFAULT_DIG_ROWS, DEFAULT_MARATHON_LEVELS, DEFAULT_SPRINT_LINES,
+    DEFAULT_ULTRA_SECONDS, MAX_DIG_ROWS, MAX_ULTRA_SECONDS,
+};
+use fetris_protocol::lock_delay::{
+    LockDelaySettings, DEFAULT_LOCK_DELAY_MS, DEFAULT_MAX_LOCK_RESETS,
+};
+use fetris_protocol::scoring::DEFAULT_LINES_PER_LEVEL;
 use std::net::TcpListener;
 use std::sync::mpsc;
 use std::{env, thread};
@@ -8,6 +16,8 @@ mod consts;
 mod game;
 mod network;
 
+use game::garbage::DEFAULT_MESSINESS;
+
 fn main() -> Result<(), std::io::Error> {
     let cli_matches = App::new("Fetris server")
         .version(env!("CARGO_PKG_VERSION"))
@@ -35,6 +45,179 @@ fn main() -> Result<(), std::io::Error> {
                 ))
                 .value_name("SIZE"),
         )
+        .arg(
+            Arg::with_name("Mode")
+                .short("m")
+                .long("mode")
+                .takes_value(true)
+                .possible_values(&["versus", "sprint", "ultra", "marathon", "dig", "survival"])
+                .help(&format!(
+                    "The game mode of the clients not choosing one (default: {})",
+                    consts::DEFAULT_MODE
+                ))
+                .value_name("MODE"),
+        )
+        .arg(
+            Arg::with_name("Sprint Lines")
+                .long("sprint-lines")
+                .takes_value(true)
+                .help(&format!(
+                    "The number of lines to clear in the default sprint (default: {})",
+                    DEFAULT_SPRINT_LINES
+                ))
+                .value_name("LINES"),
+        )
+        .arg(
+            Arg::with_name("Ultra Time")
+                .long("ultra-time")
+                .takes_value(true)
+                .help(&format!(
+                    "The duration of the default ultra game (default: {})",
+                    DEFAULT_ULTRA_SECONDS
+                ))
+                .value_name("SECONDS"),
+        )
+        .arg(
+            Arg::with_name("Level Cap")
+                .long("level-cap")
+                .takes_value(true)
+                .help(&format!(
+                    "The last level of the default marathon, 0 for endless (default: {})",
+                    DEFAULT_MARATHON_LEVELS
+                ))
+                .value_name("LEVEL"),
+        )
+        .arg(
+            Arg::with_name("Dig Rows")
+                .long("dig-rows")
+                .takes_value(true)
+                .help(&format!(
+                    "The number of garbage rows to dig through in the default dig (default: {})",
+                    DEFAULT_DIG_ROWS
+                ))
+                .value_name("ROWS"),
+        )
+        .arg(
+            Arg::with_name("Dig Rise")
+                .long("dig-rise")
+                .takes_value(true)
+                .help("Raise a garbage row every SECONDS while digging")
+                .value_name("SECONDS"),
+        )
+        .arg(
+            Arg::with_name("Randomizer")
+                .short("r")
+                .long("randomizer")
+                .takes_value(true)
+                .possible_values(&["7bag", "14bag", "random", "tgm", "nes"])
+                .help(&format!(
+                    "The piece randomizer used in the pools (default: {})",
+                    consts::DEFAULT_RANDOMIZER
+                ))
+                .value_name("RANDOMIZER"),
+        )
+        .arg(
+            Arg::with_name("Attack Table")
+                .short("a")
+                .long("attack-table")
+                .takes_value(true)
+                .possible_values(&["t99", "ppt", "tetrio"])
+                .help(&format!(
+                    "The garbage lines sent for each line clear (default: {})",
+                    consts::DEFAULT_ATTACK_TABLE
+                ))
+                .value_name("TABLE"),
+        )
+        .arg(
+            Arg::with_name("Garbage")
+                .long("garbage")
+                .takes_value(true)
+                .help(&format!(
+                    "Where the holes of the garbage lines are: clean, messy[:PERCENT] or cheese \
+                     (default: {}, {}% for messy)",
+                    consts::DEFAULT_GARBAGE,
+                    DEFAULT_MESSINESS
+                ))
+                .value_name("MODE"),
+        )
+        .arg(
+            Arg::with_name("Targeting")
+                .short("t")
+                .long("targeting")
+                .takes_value(true)
+                .possible_values(&["even", "random", "attackers", "kos", "badges"])
+                .help(&format!(
+                    "How the receiver of an attack is chosen (default: {})",
+                    consts::DEFAULT_TARGETING
+                ))
+                .value_name("STRATEGY"),
+        )
+        .arg(
+            Arg::with_name("Gravity")
+                .short("g")
+                .long("gravity")
+                .takes_value(true)
+                .possible_values(&["classic", "guideline", "nes"])
+                .help(&format!(
+                    "The gravity curve followed as the level goes up (default: {})",
+                    consts::DEFAULT_GRAVITY
+                ))
+                .value_name("CURVE"),
+        )
+        .arg(
+            Arg::with_name("Gravity Timer")
+                .long("gravity-timer")
+                .takes_value(true)
+                .help("Raise the gravity level every SECONDS in the pools, even without clearing lines")
+                .value_name("SECONDS"),
+        )
+        .arg(
+            Arg::with_name("Idle Timeout")
+                .long("idle-timeout")
+                .takes_value(true)
+                .help(&format!(
+                    "Kill the players who send nothing for SECONDS, 0 to never kill them (default: {})",
+                    consts::DEFAULT_IDLE_TIMEOUT
+                ))
+                .value_name("SECONDS"),
+        )
+        .arg(
+            Arg::with_name("Lines Per Level")
+                .long("lines-per-level")
+                .takes_value(true)
+                .help(&format!(
+                    "The number of lines to clear to reach the next level (default: {})",
+                    DEFAULT_LINES_PER_LEVEL
+                ))
+                .value_name("LINES"),
+        )
+        .arg(
+            Arg::with_name("Lock Delay")
+                .long("lock-delay")
+                .takes_value(true)
+                .help(&format!(
+                    "Time in ms a tetrimino can stay on the ground before locking (default: {})",
+                    DEFAULT_LOCK_DELAY_MS
+                ))
+                .value_name("MS"),
+        )
+        .arg(
+            Arg::with_name("Lock Resets")
+                .long("lock-resets")
+                .takes_value(true)
+                .help(&format!(
+                    "The number of moves or rotations restarting the lock delay (default: {})",
+                    DEFAULT_MAX_LOCK_RESETS
+                ))
+                .value_name("COUNT"),
+        )
+        .arg(
+            Arg::with_name("Seed")
+                .long("seed")
+                .takes_value(true)
+                .help("Seed used by every pool, for reproducible games (default: random per pool)")
+                .value_name("SEED"),
+        )
         .get_matches();
 
     let listening_port = cli_matches
@@ -51,6 +234,127 @@ fn main() -> Result<(), std::io::Error> {
         panic!("Invalid Pool Size");
     }
 
+    let sprint_lines = cli_matches
+        .value_of("Sprint Lines")
+        .map_or(Ok(DEFAULT_SPRINT_LINES), |l| l.parse())
+        .unwrap_or_else(|_| panic!("Invalid Sprint Lines"));
+
+    if sprint_lines < 1 {
+        panic!("Invalid Sprint Lines");
+    }
+
+    let ultra_seconds = cli_matches
+        .value_of("Ultra Time")
+        .map_or(Ok(DEFAULT_ULTRA_SECONDS), |t| t.parse())
+        .unwrap_or_else(|_| panic!("Invalid Ultra Time"));
+
+    if !(1..=MAX_ULTRA_SECONDS).contains(&ultra_seconds) {
+        panic!("Invalid Ultra Time");
+    }
+
+    let level_cap = cli_matches
+        .value_of("Level Cap")
+        .map_or(Ok(DEFAULT_MARATHON_LEVELS), |l| l.parse())
+        .unwrap_or_else(|_| panic!("Invalid Level Cap"));
+
+    let dig_rows = cli_matches
+        .value_of("Dig Rows")
+        .map_or(Ok(DEFAULT_DIG_ROWS), |r| r.parse())
+        .unwrap_or_else(|_| panic!("Invalid Dig Rows"));
+
+    if !(1..=MAX_DIG_ROWS).contains(&dig_rows) {
+        panic!("Invalid Dig Rows");
+    }
+
+    // Played by the clients asking for a game without choosing its mode
+    let mode = match cli_matches
+        .value_of("Mode")
+        .unwrap_or(consts::DEFAULT_MODE)
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid Mode"))
+    {
+        GameMode::Sprint(_) => GameMode::Sprint(sprint_lines),
+        GameMode::Ultra(_) => GameMode::Ultra(ultra_seconds),
+        GameMode::Marathon(_) => GameMode::Marathon(Some(level_cap).filter(|l| *l > 0)),
+        GameMode::Dig(_) => GameMode::Dig(dig_rows),
+        mode => mode,
+    };
+
+    let dig_rise = cli_matches
+        .value_of("Dig Rise")
+        .map(|t| t.parse().unwrap_or_else(|_| panic!("Invalid Dig Rise")))
+        .filter(|t| *t > 0);
+
+    let randomizer = cli_matches
+        .value_of("Randomizer")
+        .unwrap_or(consts::DEFAULT_RANDOMIZER)
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid Randomizer"));
+
+    let attack_table = cli_matches
+        .value_of("Attack Table")
+        .unwrap_or(consts::DEFAULT_ATTACK_TABLE)
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid Attack Table"));
+
+    let garbage_mode = cli_matches
+        .value_of("Garbage")
+        .unwrap_or(consts::DEFAULT_GARBAGE)
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid Garbage"));
+
+    let targeting = cli_matches
+        .value_of("Targeting")
+        .unwrap_or(consts::DEFAULT_TARGETING)
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid Targeting"));
+
+    let gravity_curve = cli_matches
+        .value_of("Gravity")
+        .unwrap_or(consts::DEFAULT_GRAVITY)
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid Gravity"));
+
+    let gravity_timer = cli_matches
+        .value_of("Gravity Timer")
+        .map(|t| {
+            t.parse()
+                .unwrap_or_else(|_| panic!("Invalid Gravity Timer"))
+        })
+        .filter(|t| *t > 0);
+
+    let idle_timeout = Some(
+        cli_matches
+            .value_of("Idle Timeout")
+            .map_or(Ok(consts::DEFAULT_IDLE_TIMEOUT), |t| t.parse())
+            .unwrap_or_else(|_| panic!("Invalid Idle Timeout")),
+    )
+    .filter(|t| *t > 0);
+
+    let lines_per_level = cli_matches
+        .value_of("Lines Per Level")
+        .map_or(Ok(DEFAULT_LINES_PER_LEVEL), |l| l.parse())
+        .unwrap_or_else(|_| panic!("Invalid Lines Per Level"));
+
+    if lines_per_level < 1 {
+        panic!("Invalid Lines Per Level");
+    }
+
+    let lock_delay = LockDelaySettings {
+        delay_ms: cli_matches
+            .value_of("Lock Delay")
+            .map_or(Ok(DEFAULT_LOCK_DELAY_MS), |d| d.parse())
+            .unwrap_or_else(|_| panic!("Invalid Lock Delay")),
+        max_resets: cli_matches
+            .value_of("Lock Resets")
+            .map_or(Ok(DEFAULT_MAX_LOCK_RESETS), |r| r.parse())
+            .unwrap_or_else(|_| panic!("Invalid Lock Resets")),
+    };
+
+    let seed = cli_matches
+        .value_of("Seed")
+        .map(|s| s.parse().unwrap_or_else(|_| panic!("Invalid Seed")));
+
     let listener = TcpListener::bind(&format!("0.0.0.0:{}", listening_port))?;
     println!("Listening on port {}", listening_port);
     let stream_list = network::StreamList::new();
@@ -58,16 +362,37 @@ fn main() -> Result<(), std::io::Error> {
 
     {
         let stream_list = stream_list.clone();
-        let options = game::Options { pool_size };
+        let options = game::Options {
+            pool_size,
+            mode,
+            seed,
+            randomizer,
+            attack_table,
+            garbage_mode,
+            targeting,
+            gravity_curve,
+            gravity_timer,
+            dig_rise,
+            idle_timeout,
+            lines_per_level,
+            lock_delay,
+        };
 
         thread::spawn(move || game::game_main_thread(stream_list, receiver, options));
     }
 
     for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("Connection failed: {}", err);
+                continue;
+            }
+        };
         let stream_list = stream_list.clone();
         let sender = sender.clone();
 
-        thread::spawn(move || client_handler::client_handler(stream.unwrap(), stream_list, sender));
+        thread::spawn(move || client_handler::client_handler(stream, stream_list, sender));
     }
     Ok(())
 }
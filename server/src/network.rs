@@ -1,5 +1,6 @@
-use fetris_protocol::{ClientRequest, ServerRequest};
+use fetris_protocol::{ClientRequest, ProtocolError, ServerRequest};
 use std::collections::HashMap;
+use std::fmt;
 use std::io::Write;
 use std::net::{SocketAddr, TcpStream};
 use std::sync::{Arc, Mutex};
@@ -26,6 +27,17 @@ pub struct StreamList(Arc<Mutex<HashMap<SocketAddr, TcpStream>>>);
 pub enum SendStreamError {
     UnknownAddr,
     CommunicationError(std::io::Error),
+    EncodingError(ProtocolError),
+}
+
+impl fmt::Display for SendStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownAddr => write!(f, "unknown address"),
+            Self::CommunicationError(err) => write!(f, "{}", err),
+            Self::EncodingError(err) => write!(f, "{}", err),
+        }
+    }
 }
 
 impl StreamList {
@@ -58,6 +70,15 @@ impl StreamList {
         all_streams.remove(addr);
     }
 
+    // The client handler then sees the stream closing, like any disconnection
+    pub fn shutdown_stream(&self, addr: &SocketAddr) {
+        let all_streams = self.0.lock().unwrap();
+
+        if let Some(stream) = all_streams.get(addr) {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
     pub fn send_to(
         &self,
         addr: &SocketAddr,
@@ -66,9 +87,9 @@ impl StreamList {
         let all_streams = self.0.lock().unwrap();
 
         if let Some(mut stream) = all_streams.get(&addr) {
-            let request_bytes = request.into_bytes();
+            let request_bytes = request.to_bytes().map_err(SendStreamError::EncodingError)?;
             println!("{} bytes sent", request_bytes.len());
-            if let Err(err) = stream.write(&request_bytes) {
+            if let Err(err) = stream.write_all(&request_bytes) {
                 return Err(SendStreamError::CommunicationError(err));
             }
             Ok(())
@@ -76,17 +97,6 @@ impl StreamList {
             return Err(SendStreamError::UnknownAddr);
         }
     }
-
-    pub fn send_to_all(&self, request: ServerRequest) {
-        let all_streams = self.0.lock().unwrap();
-        for mut stream in all_streams.values() {
-            let request_bytes = request.clone().into_bytes();
-            println!("{} bytes sent", request_bytes.len());
-            if let Err(err) = stream.write(&request_bytes) {
-                eprintln!("Communication error: {}", err);
-            }
-        }
-    }
 }
 
 impl Clone for StreamList {